@@ -18,142 +18,251 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-#![feature(test)]
-extern crate test;
+//! Throughput benchmarks over generated multi-megabyte corpora, runnable on
+//! stable Rust with `cargo bench`. Arguments filter the benchmarks that are
+//! run by substring, and the size of each corpus in megabytes can be set with
+//! the `TOKESIES_BENCH_MB` environment variable.
+
 extern crate tokesies;
 
-#[cfg(test)]
-mod tests {
-    use tokesies::*;
-    use test::Bencher;
-
-    static INPUT: &'static str = "In addition to conventional static typing, before version 0.4, Rust also supported \
-     typestates. The typestate system modeled assertions before and after program statements, \
-     through use of a special check statement. Discrepancies could be discovered at compile time, \
-     rather than when a program was running, as might be the case with assertions in C or C++ \
-     code. The typestate concept was not unique to Rust, as it was first introduced in the \
-     language NIL. Typestates were removed because in practice they found little use, though the \
-     same functionality can still be achieved with branding patterns.
-
-The style changed between \
-     0.2, 0.3 and 0.4. Version 0.2 introduced classes for the first time, with version 0.3 adding \
-     a number of features including destructors and polymorphism through the use of interfaces. \
-     In Rust 0.4, traits were added as a means to provide inheritance; In January 2014, the \
-     editor-in-chief of Dr Dobb's, Andrew Binstock, commented on Rust's chances to become a \
-     competitor to C++.";
-
-    #[bench]
-    fn bench_defaults_case(b: &mut Bencher) {
-        b.iter(|| {
-            FilteredTokenizer::new(filters::DefaultFilter {}, INPUT).last()
-        });
+use std::env;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use tokesies::*;
+use tokesies::token_filters::TokenFilterExt;
+
+const MIN_DURATION: Duration = Duration::from_millis(500);
+const MIN_ITERATIONS: u32 = 3;
+
+static LATIN: &[&str] = &[
+    "the", "of", "and", "to", "in", "is", "that", "for", "it", "as", "was", "with", "be", "by",
+    "on", "not", "he", "this", "are", "or", "his", "from", "at", "which", "but", "have",
+    "typestate", "compile", "running", "assertions", "Rust", "language", "editor-in-chief",
+    "don't", "fn_name", "parseHttpRequest", "0.4", "1,000", "50%", "v1.2.3", "naïve", "café",
+    "https://example.com/path?q=1", "someone@example.org", "#hashtag", "@mention",
+];
+
+static ARABIC: &[&str] = &[
+    "في", "من", "على", "إلى", "أن", "هذا", "التي", "الذي", "كان", "مع", "عن", "اللغة",
+    "البرمجة", "المترجم", "النظام", "الكتابة", "العربية", "٢٠٢٤", "مرحبا", "بالعالم",
+];
+
+static EMOJI: &[&str] = &[
+    "😀", "👍🏽", "👨‍👩‍👧‍👦", "🇬🇧", "❤️", "🎉", "🚀", "1️⃣", "🏳️‍🌈", "🤔",
+];
+
+/// A xorshift generator, such that corpora are identical between runs.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 as usize
     }
 
-    #[bench]
-    fn bench_hash_set(b: &mut Bencher) {
-        b.iter(|| {
-            FilteredTokenizer::new(
-                filters::HashFilter {
-                    drop_chars: [' ', '\t', '\n', '\r', '\u{C}'].iter().cloned().collect(),
-                    keep_chars: [
-                        '#',
-                        '!',
-                        '\\',
-                        '"',
-                        '%',
-                        '&',
-                        '\'',
-                        '(',
-                        ')',
-                        '*',
-                        '+',
-                        '-',
-                        '.',
-                        '/',
-                        ':',
-                        ';',
-                        '<',
-                        '=',
-                        '>',
-                        '?',
-                        '@',
-                        '[',
-                        ']',
-                        '^',
-                        '_',
-                        '`',
-                        '{',
-                        '|',
-                        '}',
-                        '~',
-                        '\u{201C}',
-                        '\u{201D}',
-                        '\u{2033}',
-                    ].iter()
-                        .cloned()
-                        .collect(),
-                },
-                INPUT,
-            ).last()
-        });
+    fn pick<'a>(&mut self, words: &[&'a str]) -> &'a str {
+        words[self.next() % words.len()]
     }
+}
 
-    #[bench]
-    fn bench_huge_vec(b: &mut Bencher) {
-        b.iter(|| {
-            let mut filter = filters::VecFilter {
-                keep_chars: Vec::new(),
-                drop_chars: Vec::new(),
-            };
-
-            filter.add_drop(' ');
-            filter.add_drop('\t');
-            filter.add_drop('\n');
-            filter.add_drop('\r');
-            filter.add_drop('\u{C}');
-
-            filter.add_keep('#');
-            filter.add_keep('!');
-            filter.add_keep('\\');
-            filter.add_keep('"');
-            filter.add_keep('%');
-            filter.add_keep('&');
-            filter.add_keep('\'');
-            filter.add_keep('(');
-            filter.add_keep(')');
-            filter.add_keep('*');
-            filter.add_keep('+');
-            filter.add_keep('-');
-            filter.add_keep('.');
-            filter.add_keep('/');
-            filter.add_keep(':');
-            filter.add_keep(';');
-            filter.add_keep('<');
-            filter.add_keep('=');
-            filter.add_keep('>');
-            filter.add_keep('?');
-            filter.add_keep('@');
-            filter.add_keep('[');
-            filter.add_keep(']');
-            filter.add_keep('^');
-            filter.add_keep('_');
-            filter.add_keep('`');
-            filter.add_keep('{');
-            filter.add_keep('|');
-            filter.add_keep('}');
-            filter.add_keep('~');
-            filter.add_keep('\u{201C}');
-            filter.add_keep('\u{201D}');
-            filter.add_keep('\u{2033}');
-
-            FilteredTokenizer::new(filter, INPUT).last()
-        });
+/// Generates sentences of words from `word` until the corpus reaches `size`
+/// bytes, separated by `space` and ended by `stop`.
+fn generate<W>(size: usize, space: &str, stop: &str, mut word: W) -> String
+where
+    W: FnMut(&mut Rng) -> String,
+{
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut corpus = String::with_capacity(size + 64);
+    while corpus.len() < size {
+        let words = 4 + rng.next() % 16;
+        for i in 0..words {
+            if i > 0 {
+                corpus.push_str(space);
+            }
+            corpus.push_str(&word(&mut rng));
+            if i + 1 < words && rng.next() % 10 == 0 {
+                corpus.push(',');
+            }
+        }
+        corpus.push_str(stop);
+        corpus.push(if rng.next() % 8 == 0 { '\n' } else { ' ' });
+    }
+    corpus
+}
+
+fn corpora(size: usize) -> Vec<(&'static str, String)> {
+    vec![
+        ("latin", generate(size, " ", ".", |rng| rng.pick(LATIN).to_string())),
+        (
+            "cjk",
+            generate(size, "", "。", |rng| {
+                let len = 1 + rng.next() % 4;
+                (0..len)
+                    .map(|_| std::char::from_u32(0x4E00 + (rng.next() % 0x5000) as u32).unwrap())
+                    .collect()
+            }),
+        ),
+        ("arabic", generate(size, " ", ".", |rng| rng.pick(ARABIC).to_string())),
+        (
+            "emoji",
+            generate(size, " ", "!", |rng| if rng.next() % 2 == 0 {
+                rng.pick(EMOJI).to_string()
+            } else {
+                rng.pick(LATIN).to_string()
+            }),
+        ),
+    ]
+}
+
+fn hash_filter() -> filters::HashFilter {
+    filters::HashFilter {
+        drop_chars: [' ', '\t', '\n', '\r', '\u{C}'].iter().cloned().collect(),
+        keep_chars: "#!\\\"%&'()*+-./:;<=>?@[]^_`{|}~\u{201C}\u{201D}\u{2033}"
+            .chars()
+            .collect(),
+    }
+}
+
+fn vec_filter() -> filters::VecFilter {
+    let mut filter = filters::VecFilter {
+        keep_chars: Vec::new(),
+        drop_chars: Vec::new(),
+    };
+    for c in " \t\n\r\u{C}".chars() {
+        filter.add_drop(c);
     }
+    for c in "#!\\\"%&'()*+-./:;<=>?@[]^_`{|}~\u{201C}\u{201D}\u{2033}".chars() {
+        filter.add_keep(c);
+    }
+    filter
+}
+
+/// Wraps a filter without exposing its ASCII table, such that it is tokenized
+/// a char at a time, for comparing against the table path on the same corpus.
+struct CharFilter<F>(F);
+
+impl<F: filters::Filter> filters::Filter for CharFilter<F> {
+    fn on_char(&self, c: &char) -> (bool, bool) {
+        self.0.on_char(c)
+    }
+}
 
-    #[bench]
-    fn bench_whitespace_case(b: &mut Bencher) {
-        b.iter(|| {
-            FilteredTokenizer::new(filters::WhitespaceFilter {}, INPUT).last()
+fn default_tokenizer<'a>(input: &'a str) -> FilteredTokenizer<'a, filters::DefaultFilter> {
+    FilteredTokenizer::new(filters::DefaultFilter {}, input)
+}
+
+/// Runs a benchmark that returns the number of tokens it produced until it
+/// has run for long enough, and prints its throughput.
+fn run<B: FnMut(&str) -> usize>(corpus: &str, name: &str, input: &str, mut bench: B) {
+    let mut tokens = bench(black_box(input));
+    let mut iterations = 0;
+    let start = Instant::now();
+    while iterations < MIN_ITERATIONS || start.elapsed() < MIN_DURATION {
+        tokens = bench(black_box(input));
+        iterations += 1;
+    }
+    let seconds = start.elapsed().as_secs_f64() / f64::from(iterations);
+    println!(
+        "{:<8} {:<24} {:>9.1} MB/s {:>13.0} tokens/s",
+        corpus,
+        name,
+        input.len() as f64 / seconds / 1_000_000.0,
+        tokens as f64 / seconds,
+    );
+}
+
+fn main() {
+    let patterns: Vec<String> = env::args().skip(1).filter(|a| !a.starts_with("--")).collect();
+    let megabytes = env::var("TOKESIES_BENCH_MB")
+        .ok()
+        .and_then(|mb| mb.parse().ok())
+        .unwrap_or(4);
+
+    let hash = hash_filter();
+    let vec = vec_filter();
+    let table = filters::TableFilter::new(hash_filter());
+
+    for (corpus, input) in corpora(megabytes << 20) {
+        let input = input.as_str();
+        let bench = |name: &str, bench: &mut dyn FnMut(&str) -> usize| {
+            let id = format!("{}/{}", corpus, name);
+            if patterns.is_empty() || patterns.iter().any(|p| id.contains(p.as_str())) {
+                run(corpus, name, input, bench);
+            }
+        };
+
+        // Filters.
+        bench("whitespace", &mut |i| {
+            FilteredTokenizer::new(filters::WhitespaceFilter {}, i).count()
+        });
+        bench("whitespace_chars", &mut |i| {
+            FilteredTokenizer::new(CharFilter(filters::WhitespaceFilter {}), i).count()
+        });
+        bench("default", &mut |i| FilteredTokenizer::new(filters::DefaultFilter {}, i).count());
+        bench("default_chars", &mut |i| {
+            FilteredTokenizer::new(CharFilter(filters::DefaultFilter {}), i).count()
+        });
+        bench("hash", &mut |i| FilteredTokenizer::new(&hash, i).count());
+        bench("vec", &mut |i| FilteredTokenizer::new(&vec, i).count());
+        bench("table", &mut |i| FilteredTokenizer::new(&table, i).count());
+        bench("number", &mut |i| {
+            FilteredTokenizer::new(filters::NumberFilter::default(), i).count()
+        });
+        bench("url", &mut |i| FilteredTokenizer::new(filters::UrlFilter::default(), i).count());
+        bench("social", &mut |i| {
+            FilteredTokenizer::new(filters::SocialFilter::default(), i).count()
+        });
+        bench("quote", &mut |i| {
+            FilteredTokenizer::new(filters::QuoteFilter::default(), i).count()
+        });
+
+        // Tokenizer modes.
+        bench("graphemes", &mut |i| {
+            FilteredTokenizer::new(filters::DefaultFilter {}, i).by_graphemes().count()
+        });
+        bench("utf16", &mut |i| {
+            FilteredTokenizer::new(filters::DefaultFilter {}, i)
+                .offset_unit(OffsetUnit::Utf16)
+                .count()
+        });
+        bench("track_lines", &mut |i| {
+            FilteredTokenizer::new(filters::DefaultFilter {}, i).track_lines(4).count()
+        });
+        bench("lossless", &mut |i| {
+            FilteredTokenizer::new(filters::DefaultFilter {}, i).lossless().count()
+        });
+        bench("reverse", &mut |i| {
+            FilteredTokenizer::new(filters::DefaultFilter {}, i).rev().count()
+        });
+        bench("parallel", &mut |i| {
+            parallel::ParallelTokenizer::new(default_tokenizer).tokenize(i).len()
+        });
+
+        // Analyzer stages.
+        bench("identifier_split", &mut |i| {
+            FilteredTokenizer::new(filters::WhitespaceFilter {}, i)
+                .filter_tokens(token_filters::IdentifierSplitFilter::default())
+                .count()
+        });
+        bench("url_components", &mut |i| {
+            FilteredTokenizer::new(filters::UrlFilter::default(), i)
+                .filter_tokens(token_filters::UrlComponentFilter)
+                .count()
+        });
+        bench("sentences", &mut |i| {
+            let mut tokens = 0;
+            for sentence in sentences::SentenceTokenizer::new(i) {
+                tokens += sentence.tokenize(filters::DefaultFilter {}, tokens).count();
+            }
+            tokens
+        });
+        bench("detokenize", &mut |i| {
+            let tokens: Vec<Token> = FilteredTokenizer::new(filters::DefaultFilter {}, i).collect();
+            black_box(detokenizer::Detokenizer::new(i).detokenize(&tokens));
+            tokens.len()
         });
     }
 }
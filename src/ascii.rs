@@ -0,0 +1,86 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use filters::AsciiTable;
+
+/// Returns the length of the run of ASCII bytes at the start of `bytes` that
+/// are part of a token according to `table`, stopping at the first delimiter
+/// or non-ASCII byte.
+pub(crate) fn word_len(table: &AsciiTable, bytes: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if bytes.len() >= 16 && has_ssse3() {
+            // SAFETY: SSSE3 support was either detected at runtime or, without
+            // std, is guaranteed by the target features, and `word_len_ssse3`
+            // only loads blocks that lie within `bytes` and `table`.
+            return unsafe { word_len_ssse3(table, bytes) };
+        }
+    }
+    word_len_from(table, bytes, 0)
+}
+
+fn word_len_from(table: &AsciiTable, bytes: &[u8], from: usize) -> usize {
+    match bytes[from..].iter().position(|b| !table.is_word(*b)) {
+        Some(i) => from + i,
+        None => bytes.len(),
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "std"))]
+fn has_ssse3() -> bool {
+    is_x86_feature_detected!("ssse3")
+}
+
+// Without std the CPU cannot be queried, so SSSE3 is only used when the target
+// guarantees it.
+#[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+fn has_ssse3() -> bool {
+    cfg!(target_feature = "ssse3")
+}
+
+/// Scans sixteen bytes at a time by looking up the low and high nibble of
+/// each byte, such that a byte ends a token when the bitmask of high nibbles
+/// for its low nibble contains its high nibble, or when it is not ASCII.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn word_len_ssse3(table: &AsciiTable, bytes: &[u8]) -> usize {
+    use core::arch::x86_64::*;
+
+    // SAFETY: `nibbles` is exactly 16 bytes, and the load is unaligned.
+    let lo_table = _mm_loadu_si128(table.nibbles.as_ptr() as *const __m128i);
+    let hi_table = _mm_setr_epi8(1, 2, 4, 8, 16, 32, 64, -128, 0, 0, 0, 0, 0, 0, 0, 0);
+    let nibble = _mm_set1_epi8(0x0f);
+
+    let mut i = 0;
+    while i + 16 <= bytes.len() {
+        // SAFETY: the loop condition keeps the 16 bytes from `i` within
+        // `bytes`, and the load is unaligned.
+        let block = _mm_loadu_si128(bytes.as_ptr().add(i) as *const __m128i);
+        let lo = _mm_shuffle_epi8(lo_table, _mm_and_si128(block, nibble));
+        let hi = _mm_shuffle_epi8(hi_table, _mm_and_si128(_mm_srli_epi16(block, 4), nibble));
+        let word = _mm_cmpeq_epi8(_mm_and_si128(lo, hi), _mm_setzero_si128());
+        let ends = (!_mm_movemask_epi8(word) | _mm_movemask_epi8(block)) & 0xffff;
+        if ends != 0 {
+            return i + ends.trailing_zeros() as usize;
+        }
+        i += 16;
+    }
+    word_len_from(table, bytes, i)
+}
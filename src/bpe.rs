@@ -0,0 +1,483 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::Path;
+
+use filters::Filter;
+use subword::{parse_json_vocab, read_vocab, SubToken, VocabError};
+use token_filters::sub_token;
+use {FilteredTokenizer, Token, TokenKind};
+
+/// The units that a Bpe model merges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BpeLevel {
+    /// Merge the bytes of the input, as GPT-2 does, such that any input can be
+    /// encoded. Each byte is represented in the vocabulary by a printable
+    /// char, with a space represented as `Ġ`.
+    Bytes,
+
+    /// Merge the chars of the input. Chars missing from the vocabulary are
+    /// encoded as the unknown token if the model has one, and skipped
+    /// otherwise.
+    Chars,
+}
+
+/// Returns the printable chars that represent each byte in the vocabulary of
+/// a byte level model, which are the bytes themselves for printable Latin-1
+/// chars and successive chars from U+0100 for the rest.
+fn byte_chars() -> [char; 256] {
+    let mut chars = ['\0'; 256];
+    let mut next = 0x100;
+    for (b, c) in chars.iter_mut().enumerate() {
+        let printable = matches!(b, 0x21..=0x7E | 0xA1..=0xAC | 0xAE..=0xFF);
+        let code = if printable {
+            b as u32
+        } else {
+            next += 1;
+            next - 1
+        };
+        *c = char::from_u32(code).unwrap();
+    }
+    chars
+}
+
+/// Splits an input into the words that a Bpe model merges within, which are
+/// the tokens of a tokenizer each preceded by the input discarded before it,
+/// as GPT-2 attaches a leading space to each word. Input discarded after the
+/// last token is a word of its own of kind `TokenKind::Trivia`. Words are
+/// passed to `f` as tokens over the input.
+fn for_each_word<'a, T, F>(mut tokenizer: FilteredTokenizer<'a, T>, mut f: F)
+where
+    T: Filter,
+    F: FnMut(Token<'a>),
+{
+    let input = tokenizer.input;
+    let unit = tokenizer.unit();
+    let state = tokenizer.state();
+    let (mut start_byte, mut start_offset) = (state.byte_offset, state.char_offset);
+
+    loop {
+        let (end_byte, end_offset, kind) = match tokenizer.next() {
+            Some(token) => (token.end_byte, token.end_offset, token.kind),
+            None => {
+                let state = tokenizer.state();
+                if start_byte >= state.byte_offset {
+                    return;
+                }
+                (state.byte_offset, state.char_offset, TokenKind::Trivia)
+            }
+        };
+        f(Token {
+            term: Cow::Borrowed(&input[start_byte..end_byte]),
+            start_offset,
+            end_offset,
+            offset_unit: unit,
+            start_byte,
+            end_byte,
+            position: 0,
+            kind,
+            location: None,
+        });
+        start_byte = end_byte;
+        start_offset = end_offset;
+    }
+}
+
+/// A Byte-Pair Encoding model, which splits words into bytes or chars and then
+/// repeatedly merges the adjacent pair of pieces that was learned earliest
+/// until no learned pair remains.
+///
+/// Words are taken from a FilteredTokenizer along with the discarded input
+/// that precedes them, such that decoding the ids of an encoded input gives
+/// back the input exactly, so long as a char level model knows every char of
+/// the input.
+pub struct Bpe {
+    level: BpeLevel,
+    vocab: HashMap<String, u32>,
+    pieces: HashMap<u32, String>,
+    merges: Vec<(String, String)>,
+    ranks: HashMap<(u32, u32), (usize, u32)>,
+    byte_ids: [Option<u32>; 256],
+    char_bytes: HashMap<char, u8>,
+    unk_id: Option<u32>,
+}
+
+impl Bpe {
+    /// Creates a model from a vocabulary of pieces and their ids, and a list
+    /// of merges in the order they were learned. Both pieces of each merge and
+    /// the piece they merge into must be in the vocabulary.
+    pub fn new(
+        vocab: HashMap<String, u32>,
+        merges: Vec<(String, String)>,
+        level: BpeLevel,
+    ) -> Result<Self, VocabError> {
+        let id = |piece: &str| match vocab.get(piece) {
+            Some(id) => Ok(*id),
+            None => Err(VocabError::MissingToken(piece.to_string())),
+        };
+
+        let mut ranks = HashMap::new();
+        for (rank, (a, b)) in merges.iter().enumerate() {
+            let pair = (id(a)?, id(b)?);
+            let merged = id(&format!("{}{}", a, b))?;
+            ranks.entry(pair).or_insert((rank, merged));
+        }
+
+        let byte_chars = byte_chars();
+        let mut byte_ids = [None; 256];
+        for (b, c) in byte_chars.iter().enumerate() {
+            byte_ids[b] = vocab.get(c.encode_utf8(&mut [0; 4]) as &str).cloned();
+        }
+
+        Ok(Bpe {
+            level,
+            pieces: vocab.iter().map(|(piece, id)| (*id, piece.clone())).collect(),
+            vocab,
+            merges,
+            ranks,
+            byte_ids,
+            char_bytes: byte_chars.iter().enumerate().map(|(b, c)| (*c, b as u8)).collect(),
+            unk_id: None,
+        })
+    }
+
+    /// Creates a model from the contents of a `vocab.json` file, which maps
+    /// pieces to ids, and a `merges.txt` file, which lists one merge per line
+    /// as two pieces separated by a space, after an optional `#version`
+    /// header.
+    pub fn from_vocab(vocab: &str, merges: &str, level: BpeLevel) -> Result<Self, VocabError> {
+        let vocab = parse_json_vocab(vocab)?;
+        let mut pairs = Vec::new();
+        for (i, line) in merges.lines().enumerate() {
+            if line.is_empty() || (i == 0 && line.starts_with("#version")) {
+                continue;
+            }
+            let mut parts = line.split(' ');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(a), Some(b), None) if !a.is_empty() && !b.is_empty() => {
+                    pairs.push((a.to_string(), b.to_string()))
+                }
+                _ => {
+                    return Err(VocabError::Parse {
+                        line: i + 1,
+                        message: "expected two pieces separated by a space".to_string(),
+                    })
+                }
+            }
+        }
+        Bpe::new(vocab, pairs, level)
+    }
+
+    /// Creates a model from a `vocab.json` file and a `merges.txt` file.
+    pub fn from_files<P: AsRef<Path>, Q: AsRef<Path>>(
+        vocab: P,
+        merges: Q,
+        level: BpeLevel,
+    ) -> Result<Self, VocabError> {
+        Bpe::from_vocab(&read_vocab(vocab)?, &read_vocab(merges)?, level)
+    }
+
+    /// Sets the token that chars missing from the vocabulary are encoded as,
+    /// which must be in the vocabulary.
+    pub fn with_unk_token(mut self, unk_token: &str) -> Result<Self, VocabError> {
+        match self.vocab.get(unk_token) {
+            Some(id) => self.unk_id = Some(*id),
+            None => return Err(VocabError::MissingToken(unk_token.to_string())),
+        }
+        Ok(self)
+    }
+
+    /// Returns the vocabulary of the model.
+    pub fn vocab(&self) -> &HashMap<String, u32> {
+        &self.vocab
+    }
+
+    /// Returns the merges of the model in the order they were learned.
+    pub fn merges(&self) -> &[(String, String)] {
+        &self.merges
+    }
+
+    /// Returns the id of a piece.
+    pub fn id(&self, piece: &str) -> Option<u32> {
+        self.vocab.get(piece).cloned()
+    }
+
+    /// Splits a word into the byte ranges and ids of its pieces.
+    fn merge(&self, word: &str, symbols: &mut Vec<(usize, usize, u32)>) {
+        symbols.clear();
+        match self.level {
+            BpeLevel::Bytes => {
+                for (i, b) in word.bytes().enumerate() {
+                    if let Some(id) = self.byte_ids[b as usize].or(self.unk_id) {
+                        symbols.push((i, i + 1, id));
+                    }
+                }
+            }
+            BpeLevel::Chars => {
+                let mut buf = [0; 4];
+                for (i, c) in word.char_indices() {
+                    let id = self.vocab.get(c.encode_utf8(&mut buf) as &str).cloned();
+                    if let Some(id) = id.or(self.unk_id) {
+                        symbols.push((i, i + c.len_utf8(), id));
+                    }
+                }
+            }
+        }
+
+        loop {
+            let best = symbols
+                .windows(2)
+                .enumerate()
+                .filter_map(|(i, w)| self.ranks.get(&(w[0].2, w[1].2)).map(|r| (r.0, i, r.1)))
+                .min();
+            let (_, i, merged) = match best {
+                Some(best) => best,
+                None => break,
+            };
+            symbols[i] = (symbols[i].0, symbols[i + 1].1, merged);
+            symbols.remove(i + 1);
+        }
+    }
+
+    /// Encodes the tokens of a tokenizer. Pieces of a byte level model that
+    /// cover part of a char have the offsets and term of the whole char.
+    pub fn encode<'a, T: Filter>(&self, tokenizer: FilteredTokenizer<'a, T>) -> Vec<SubToken<'a>> {
+        let mut out = Vec::new();
+        let mut symbols = Vec::new();
+        for_each_word(tokenizer, |word| {
+            self.merge(&word.term, &mut symbols);
+            for &(mut start, mut end, id) in &symbols {
+                while !word.term.is_char_boundary(start) {
+                    start -= 1;
+                }
+                while !word.term.is_char_boundary(end) {
+                    end += 1;
+                }
+                let mut piece = sub_token(&word, start, end, word.kind);
+                piece.position = out.len();
+                out.push(SubToken { token: piece, id });
+            }
+        });
+        out
+    }
+
+    /// Decodes a sequence of ids back into text. Ids missing from the
+    /// vocabulary are skipped, and bytes of a byte level model that are not
+    /// valid UTF-8 are replaced with U+FFFD.
+    pub fn decode(&self, ids: &[u32]) -> String {
+        let pieces = ids.iter().filter_map(|id| self.pieces.get(id));
+        match self.level {
+            BpeLevel::Chars => pieces.map(|p| p.as_str()).collect(),
+            BpeLevel::Bytes => {
+                let mut bytes = Vec::new();
+                for c in pieces.flat_map(|p| p.chars()) {
+                    match self.char_bytes.get(&c) {
+                        Some(b) => bytes.push(*b),
+                        None => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                    }
+                }
+                String::from_utf8_lossy(&bytes).into_owned()
+            }
+        }
+    }
+}
+
+/// Learns a Bpe model from a corpus.
+pub struct BpeTrainer {
+    /// The units to merge.
+    pub level: BpeLevel,
+
+    /// The size of the vocabulary to learn, including the initial pieces.
+    pub vocab_size: usize,
+
+    /// The number of occurrences below which a pair is not merged.
+    pub min_frequency: usize,
+
+    /// A token added to the vocabulary of a char level model, which chars not
+    /// seen during training are encoded as.
+    pub unk_token: Option<String>,
+}
+
+impl BpeTrainer {
+    /// Creates a trainer that merges pairs seen at least twice.
+    pub fn new(level: BpeLevel, vocab_size: usize) -> Self {
+        BpeTrainer {
+            level,
+            vocab_size,
+            min_frequency: 2,
+            unk_token: None,
+        }
+    }
+
+    /// Learns a model from the texts of a corpus, split into words by
+    /// tokenizers with `filter`.
+    ///
+    /// The initial vocabulary of a byte level model is every byte, and that
+    /// of a char level model is the unknown token followed by every char of
+    /// the corpus. The most frequent adjacent pair of pieces within words is
+    /// then merged until the vocabulary reaches `vocab_size`, with ties
+    /// broken by the order of the pieces so that training is deterministic.
+    pub fn train<I, S, F>(&self, corpus: I, filter: F) -> Bpe
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+        F: Filter,
+    {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for text in corpus {
+            for_each_word(FilteredTokenizer::new(&filter, text.as_ref()), |word| {
+                *counts.entry(word.term.into_owned()).or_insert(0) += 1;
+            });
+        }
+
+        let byte_chars = byte_chars();
+        let alphabet: Vec<String> = match self.level {
+            BpeLevel::Bytes => byte_chars.iter().map(|c| c.to_string()).collect(),
+            BpeLevel::Chars => {
+                let mut chars: Vec<char> = counts.keys().flat_map(|w| w.chars()).collect();
+                chars.sort();
+                chars.dedup();
+                let chars = chars.into_iter().map(|c| c.to_string());
+                self.unk_token.iter().cloned().chain(chars).collect()
+            }
+        };
+        let mut vocab: HashMap<String, u32> = HashMap::new();
+        let mut pieces: Vec<String> = Vec::new();
+        for piece in alphabet {
+            if !vocab.contains_key(&piece) {
+                vocab.insert(piece.clone(), pieces.len() as u32);
+                pieces.push(piece);
+            }
+        }
+
+        let mut buf = [0; 4];
+        let mut words: Vec<(Vec<u32>, usize)> = counts
+            .into_iter()
+            .map(|(word, count)| {
+                let symbols = match self.level {
+                    BpeLevel::Bytes => word
+                        .bytes()
+                        .map(|b| vocab[byte_chars[b as usize].encode_utf8(&mut buf) as &str])
+                        .collect(),
+                    BpeLevel::Chars => word
+                        .chars()
+                        .map(|c| vocab[c.encode_utf8(&mut buf) as &str])
+                        .collect(),
+                };
+                (symbols, count)
+            })
+            .collect();
+
+        // The number of occurrences of each pair of adjacent pieces, along
+        // with the words they occur in, are kept up to date as pieces are
+        // merged. The most frequent pair is taken from a heap, in which an
+        // entry is pushed whenever the count of a pair changes and entries
+        // with outdated counts are skipped.
+        let mut pairs: HashMap<(u32, u32), usize> = HashMap::new();
+        let mut occurrences: HashMap<(u32, u32), HashSet<usize>> = HashMap::new();
+        for (i, &(ref symbols, count)) in words.iter().enumerate() {
+            for w in symbols.windows(2) {
+                *pairs.entry((w[0], w[1])).or_insert(0) += count;
+                occurrences.entry((w[0], w[1])).or_default().insert(i);
+            }
+        }
+        let entry = |pieces: &[String], pair: (u32, u32), count: usize| {
+            let key = (pieces[pair.0 as usize].clone(), pieces[pair.1 as usize].clone());
+            (count, Reverse(key), pair)
+        };
+        let mut heap: BinaryHeap<_> = pairs.iter().map(|(&p, &c)| entry(&pieces, p, c)).collect();
+
+        let mut merges = Vec::new();
+        let mut changed = Vec::new();
+        while pieces.len() < self.vocab_size {
+            let (count, Reverse((a, b)), pair) = match heap.pop() {
+                Some(best) => best,
+                None => break,
+            };
+            if pairs.get(&pair) != Some(&count) {
+                continue;
+            }
+            if count < self.min_frequency.max(1) {
+                break;
+            }
+
+            let merged = format!("{}{}", a, b);
+            let id = match vocab.get(&merged) {
+                Some(id) => *id,
+                None => {
+                    let id = pieces.len() as u32;
+                    vocab.insert(merged.clone(), id);
+                    pieces.push(merged);
+                    id
+                }
+            };
+            merges.push((a, b));
+
+            changed.clear();
+            for i in occurrences.remove(&pair).unwrap_or_default() {
+                let (ref mut symbols, count) = words[i];
+                if !symbols.windows(2).any(|w| (w[0], w[1]) == pair) {
+                    continue;
+                }
+                for w in symbols.windows(2) {
+                    *pairs.get_mut(&(w[0], w[1])).expect("pair is counted") -= count;
+                    changed.push((w[0], w[1]));
+                }
+                let mut j = 0;
+                while j + 1 < symbols.len() {
+                    if (symbols[j], symbols[j + 1]) == pair {
+                        symbols[j] = id;
+                        symbols.remove(j + 1);
+                    }
+                    j += 1;
+                }
+                for w in symbols.windows(2) {
+                    *pairs.entry((w[0], w[1])).or_insert(0) += count;
+                    occurrences.entry((w[0], w[1])).or_default().insert(i);
+                    changed.push((w[0], w[1]));
+                }
+            }
+
+            changed.sort_unstable();
+            changed.dedup();
+            for &pair in &changed {
+                match pairs.get(&pair).cloned() {
+                    Some(0) => {
+                        pairs.remove(&pair);
+                    }
+                    Some(count) => heap.push(entry(&pieces, pair, count)),
+                    None => {}
+                }
+            }
+        }
+
+        let model = Bpe::new(vocab, merges, self.level)
+            .expect("trained merges are in the vocabulary");
+        match self.unk_token {
+            Some(ref unk) if self.level == BpeLevel::Chars => model
+                .with_unk_token(unk)
+                .expect("unknown token is in the vocabulary"),
+            _ => model,
+        }
+    }
+}
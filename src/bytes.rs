@@ -0,0 +1,200 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::fmt;
+use core::fmt::Write;
+use core::str::Utf8Chunks;
+
+use filters::Filter;
+use {FilteredTokenizer, OffsetUnit, Token, TokenKind};
+
+/// Determines how a ByteTokenizer treats sequences of bytes that are not valid
+/// UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidUtf8Policy {
+    /// Emit each invalid sequence as a token with the term U+FFFD.
+    Replace,
+
+    /// Emit each invalid sequence as a token with its bytes escaped as `\xNN`
+    /// in the term.
+    Opaque,
+
+    /// Stop tokenizing with an error at the first invalid sequence.
+    Error,
+}
+
+/// An error for a sequence of bytes that is not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidUtf8Error {
+    /// The offset in bytes of the invalid sequence.
+    pub byte_offset: usize,
+
+    /// The length in bytes of the invalid sequence.
+    pub len: usize,
+}
+
+impl fmt::Display for InvalidUtf8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid UTF-8 sequence of {} bytes at byte {}",
+            self.len,
+            self.byte_offset
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for InvalidUtf8Error {}
+
+/// Tokenizes bytes that may not be valid UTF-8, treating invalid sequences
+/// according to an InvalidUtf8Policy. Invalid sequences are split up in the
+/// same way as `String::from_utf8_lossy`, and always end a token.
+///
+/// Byte offsets are exact. In any other offset unit an invalid sequence
+/// counts as the single U+FFFD that replaces it, such that offsets agree with
+/// those of the lossily converted input.
+pub struct ByteTokenizer<'a, T: Filter> {
+    filter: T,
+    chunks: Utf8Chunks<'a>,
+    policy: InvalidUtf8Policy,
+    offset_unit: OffsetUnit,
+    valid: &'a str,
+    invalid: &'a [u8],
+    base: usize,
+    segment_offset: usize,
+    offset: usize,
+    position: usize,
+    failed: bool,
+}
+
+impl<'a, T: Filter> ByteTokenizer<'a, T> {
+    pub fn new(filter: T, input: &'a [u8], policy: InvalidUtf8Policy) -> Self {
+        ByteTokenizer {
+            filter,
+            chunks: input.utf8_chunks(),
+            policy,
+            offset_unit: OffsetUnit::Chars,
+            valid: "",
+            invalid: &[],
+            base: 0,
+            segment_offset: 0,
+            offset: 0,
+            position: 0,
+            failed: false,
+        }
+    }
+
+    /// Sets the unit in which token offsets are counted.
+    pub fn offset_unit(mut self, unit: OffsetUnit) -> Self {
+        self.offset_unit = unit;
+        self
+    }
+
+    /// Extracts the pending invalid sequence according to the policy.
+    fn take_invalid(&mut self) -> Result<Token<'a>, InvalidUtf8Error> {
+        let invalid = self.invalid;
+        let start_byte = self.base;
+        self.invalid = &[];
+        self.base += invalid.len();
+
+        let term = match self.policy {
+            InvalidUtf8Policy::Replace => Cow::Borrowed("\u{FFFD}"),
+            InvalidUtf8Policy::Opaque => {
+                let mut escaped = String::with_capacity(invalid.len() * 4);
+                for b in invalid {
+                    let _ = write!(escaped, "\\x{:02X}", b);
+                }
+                Cow::Owned(escaped)
+            }
+            InvalidUtf8Policy::Error => {
+                self.failed = true;
+                return Err(InvalidUtf8Error {
+                    byte_offset: start_byte,
+                    len: invalid.len(),
+                });
+            }
+        };
+
+        let start_offset = self.offset;
+        self.offset += match self.offset_unit {
+            OffsetUnit::Bytes => invalid.len(),
+            _ => 1,
+        };
+        let token = Token {
+            term,
+            start_offset,
+            end_offset: self.offset,
+            offset_unit: self.offset_unit,
+            start_byte,
+            end_byte: self.base,
+            position: self.position,
+            kind: TokenKind::InvalidUtf8,
+            location: None,
+        };
+        self.position += 1;
+        Ok(token)
+    }
+}
+
+impl<'a, T: Filter> Iterator for ByteTokenizer<'a, T> {
+    type Item = Result<Token<'a>, InvalidUtf8Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            if !self.valid.is_empty() {
+                let mut tokenizer = FilteredTokenizer::with_state(
+                    &self.filter,
+                    self.valid,
+                    self.segment_offset,
+                    self.offset,
+                    self.position,
+                ).offset_unit(self.offset_unit);
+                let token = tokenizer.next();
+
+                let state = tokenizer.state();
+                self.segment_offset = state.byte_offset;
+                self.offset = state.char_offset;
+                self.position = state.position;
+
+                if let Some(mut token) = token {
+                    token.start_byte += self.base;
+                    token.end_byte += self.base;
+                    return Some(Ok(token));
+                }
+                self.base += self.valid.len();
+                self.valid = "";
+            }
+            if !self.invalid.is_empty() {
+                return Some(self.take_invalid());
+            }
+
+            let chunk = self.chunks.next()?;
+            self.valid = chunk.valid();
+            self.invalid = chunk.invalid();
+            self.segment_offset = 0;
+        }
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! A declarative description of a tokenizer and the token filters applied to
+//! its output, which can be deserialized from any format supported by serde,
+//! such as JSON or YAML, in order to configure tokenization at runtime:
+//!
+//! ```json
+//! {
+//!     "filter": { "type": "url", "inner": { "type": "custom", "drop": " \n" } },
+//!     "offset_unit": "utf16",
+//!     "token_filters": [{ "type": "url_components" }]
+//! }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use filters::{self, Filter};
+use token_filters::{self, TokenFilter, TokenFilterExt};
+use {FilteredTokenizer, OffsetUnit, Token};
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_quotes() -> Vec<(char, char)> {
+    filters::QuoteFilter::default().quotes
+}
+
+/// Describes a filter, where filters that wrap another filter describe it as
+/// `inner`, which defaults to the default filter.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FilterConfig {
+    /// `filters::DefaultFilter`.
+    #[default]
+    Default,
+
+    /// `filters::WhitespaceFilter`.
+    Whitespace,
+
+    /// A filter that collects the chars of `keep` as tokens and discards the
+    /// chars of `drop`.
+    Custom {
+        #[serde(default)]
+        keep: String,
+        #[serde(default)]
+        drop: String,
+    },
+
+    /// `filters::NumberFilter`.
+    Number {
+        #[serde(default)]
+        inner: Box<FilterConfig>,
+    },
+
+    /// `filters::UrlFilter`.
+    Url {
+        #[serde(default)]
+        inner: Box<FilterConfig>,
+        #[serde(default)]
+        hostnames: bool,
+    },
+
+    /// `filters::SocialFilter`.
+    Social {
+        #[serde(default)]
+        inner: Box<FilterConfig>,
+    },
+
+    /// `filters::QuoteFilter`.
+    Quote {
+        #[serde(default)]
+        inner: Box<FilterConfig>,
+        #[serde(default = "default_quotes")]
+        quotes: Vec<(char, char)>,
+        #[serde(default = "enabled")]
+        escapes: bool,
+        #[serde(default)]
+        strip_quotes: bool,
+        #[serde(default)]
+        unescape: bool,
+    },
+}
+
+impl FilterConfig {
+    /// Creates the described filter.
+    pub fn build(&self) -> Box<dyn Filter + Send + Sync> {
+        match *self {
+            FilterConfig::Default => Box::new(filters::DefaultFilter),
+            FilterConfig::Whitespace => Box::new(filters::WhitespaceFilter),
+            FilterConfig::Custom { ref keep, ref drop } => {
+                Box::new(filters::TableFilter::from_chars(keep, drop))
+            }
+            FilterConfig::Number { ref inner } => {
+                Box::new(filters::NumberFilter::new(inner.build()))
+            }
+            FilterConfig::Url {
+                ref inner,
+                hostnames,
+            } => Box::new(filters::UrlFilter {
+                hostnames,
+                ..filters::UrlFilter::new(inner.build())
+            }),
+            FilterConfig::Social { ref inner } => {
+                Box::new(filters::SocialFilter::new(inner.build()))
+            }
+            FilterConfig::Quote {
+                ref inner,
+                ref quotes,
+                escapes,
+                strip_quotes,
+                unescape,
+            } => Box::new(filters::QuoteFilter {
+                quotes: quotes.clone(),
+                escapes,
+                strip_quotes,
+                unescape,
+                ..filters::QuoteFilter::new(inner.build())
+            }),
+        }
+    }
+}
+
+/// Describes a token filter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TokenFilterConfig {
+    /// `token_filters::UrlComponentFilter`.
+    UrlComponents,
+
+    /// `token_filters::IdentifierSplitFilter`.
+    IdentifierSplit {
+        #[serde(default = "enabled")]
+        split_on_case_change: bool,
+        #[serde(default = "enabled")]
+        split_on_numerics: bool,
+        #[serde(default = "enabled")]
+        split_on_underscores: bool,
+        #[serde(default)]
+        preserve_original: bool,
+    },
+}
+
+impl TokenFilterConfig {
+    /// Creates the described token filter.
+    pub fn build(&self) -> Box<dyn TokenFilter + Send + Sync> {
+        match *self {
+            TokenFilterConfig::UrlComponents => Box::new(token_filters::UrlComponentFilter),
+            TokenFilterConfig::IdentifierSplit {
+                split_on_case_change,
+                split_on_numerics,
+                split_on_underscores,
+                preserve_original,
+            } => Box::new(token_filters::IdentifierSplitFilter {
+                split_on_case_change,
+                split_on_numerics,
+                split_on_underscores,
+                preserve_original,
+            }),
+        }
+    }
+}
+
+/// Describes a tokenizer along with the token filters applied to its output,
+/// in order. Every field is optional.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// The filter to tokenize with.
+    pub filter: FilterConfig,
+
+    /// Whether to tokenize by grapheme cluster rather than by char.
+    pub graphemes: bool,
+
+    /// The unit in which offsets are counted, which defaults to graphemes when
+    /// tokenizing by grapheme cluster and to chars otherwise.
+    pub offset_unit: Option<OffsetUnit>,
+
+    /// The tab width with which to track lines, if lines are tracked.
+    pub track_lines: Option<usize>,
+
+    /// Whether to emit discarded chars as trivia tokens.
+    pub lossless: bool,
+
+    /// The token filters to apply, in order.
+    pub token_filters: Vec<TokenFilterConfig>,
+}
+
+impl PipelineConfig {
+    /// Creates the described pipeline.
+    pub fn build(&self) -> Pipeline {
+        Pipeline {
+            filter: self.filter.build(),
+            graphemes: self.graphemes,
+            offset_unit: self.offset_unit,
+            track_lines: self.track_lines,
+            lossless: self.lossless,
+            token_filters: self.token_filters.iter().map(|f| f.build()).collect(),
+        }
+    }
+}
+
+/// A tokenizer and token filters created from a PipelineConfig.
+pub struct Pipeline {
+    filter: Box<dyn Filter + Send + Sync>,
+    graphemes: bool,
+    offset_unit: Option<OffsetUnit>,
+    track_lines: Option<usize>,
+    lossless: bool,
+    token_filters: Vec<Box<dyn TokenFilter + Send + Sync>>,
+}
+
+impl Pipeline {
+    /// Creates a tokenizer for an input, without the token filters of the
+    /// pipeline.
+    pub fn tokenizer<'a>(
+        &'a self,
+        input: &'a str,
+    ) -> FilteredTokenizer<'a, &'a (dyn Filter + Send + Sync)> {
+        let mut tokenizer = FilteredTokenizer::new(&*self.filter, input);
+        if self.graphemes {
+            tokenizer = tokenizer.by_graphemes();
+        }
+        if let Some(unit) = self.offset_unit {
+            tokenizer = tokenizer.offset_unit(unit);
+        }
+        if let Some(tab_width) = self.track_lines {
+            tokenizer = tokenizer.track_lines(tab_width);
+        }
+        if self.lossless {
+            tokenizer = tokenizer.lossless();
+        }
+        tokenizer
+    }
+
+    /// Tokenizes an input and applies the token filters of the pipeline.
+    pub fn tokenize<'a>(&'a self, input: &'a str) -> Box<dyn Iterator<Item = Token<'a>> + 'a> {
+        let mut tokens: Box<dyn Iterator<Item = Token<'a>> + 'a> = Box::new(self.tokenizer(input));
+        for filter in &self.token_filters {
+            tokens = Box::new(tokens.filter_tokens(&**filter));
+        }
+        tokens
+    }
+}
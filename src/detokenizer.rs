@@ -0,0 +1,185 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use alloc::string::String;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use {OffsetUnit, Token};
+
+/// Converts offsets in any unit into byte offsets of an input, walking
+/// forwards from the last converted offset so that converting the offsets of
+/// a sequence of tokens is linear.
+struct OffsetCursor<'a> {
+    input: &'a str,
+    unit: OffsetUnit,
+    byte_offset: usize,
+    offset: usize,
+}
+
+impl<'a> OffsetCursor<'a> {
+    fn new(input: &'a str) -> Self {
+        OffsetCursor {
+            input,
+            unit: OffsetUnit::Chars,
+            byte_offset: 0,
+            offset: 0,
+        }
+    }
+
+    /// Returns the byte offset of `offset`, or `None` if it lies outside of
+    /// the input or within a unit of the walk, such as half of a UTF-16
+    /// surrogate pair.
+    fn byte_offset_of(&mut self, unit: OffsetUnit, offset: usize) -> Option<usize> {
+        if unit == OffsetUnit::Bytes {
+            return if self.input.is_char_boundary(offset) { Some(offset) } else { None };
+        }
+        if unit != self.unit || offset < self.offset {
+            self.unit = unit;
+            self.byte_offset = 0;
+            self.offset = 0;
+        }
+
+        while self.offset < offset {
+            let rest = &self.input[self.byte_offset..];
+            let step = if unit == OffsetUnit::Graphemes {
+                rest.graphemes(true).next()?
+            } else {
+                &rest[..rest.chars().next()?.len_utf8()]
+            };
+            self.offset += unit.measure(step);
+            self.byte_offset += step.len();
+        }
+        if self.offset == offset { Some(self.byte_offset) } else { None }
+    }
+}
+
+/// Punctuation that is not preceded by a space.
+static CLOSING: &str = ",.;:!?)]}%\u{201D}\u{2019}\u{2026}";
+
+/// Punctuation that is not followed by a space.
+static OPENING: &str = "([{$#\u{201C}\u{2018}\u{BF}\u{A1}";
+
+/// Contraction suffixes that are joined to the preceding word.
+static CONTRACTIONS: &[&str] = &["n't", "'s", "'re", "'ve", "'ll", "'d", "'m"];
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3000..=0x303F | 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF |
+            0xF900..=0xFAFF | 0xFF00..=0xFFEF | 0x20000..=0x2FA1F
+    )
+}
+
+/// Rebuilds text from a sequence of tokens, such as after tokens have been
+/// masked, replaced or translated.
+///
+/// When the original input is available, text between two adjacent tokens is
+/// reinserted whenever it is only whitespace. Otherwise, such as between
+/// tokens that were synthesized without meaningful offsets or where a token
+/// has been removed, spacing follows a set of rules: no space before closing
+/// punctuation such as `,`, `.` and `)`, no space after opening punctuation
+/// such as `(`, no space within contractions such as `n't`, and no space
+/// between CJK characters.
+pub struct Detokenizer<'a> {
+    input: Option<&'a str>,
+}
+
+impl<'a> Detokenizer<'a> {
+    /// Creates a detokenizer that reuses whitespace from the input the tokens
+    /// were extracted from.
+    pub fn new(input: &'a str) -> Self {
+        Detokenizer { input: Some(input) }
+    }
+
+    /// Creates a detokenizer that relies entirely on spacing rules.
+    pub fn without_input() -> Self {
+        Detokenizer { input: None }
+    }
+
+    /// Returns the text between the end of `prev` and the start of `next` in
+    /// the input if it is only whitespace.
+    fn original_gap(
+        &self,
+        cursor: &mut OffsetCursor<'a>,
+        prev: &Token,
+        next: &Token,
+    ) -> Option<&'a str> {
+        let input = self.input?;
+        if prev.offset_unit != next.offset_unit || prev.end_offset > next.start_offset ||
+            prev.start_offset > prev.end_offset
+        {
+            return None;
+        }
+        let start = cursor.byte_offset_of(prev.offset_unit, prev.end_offset)?;
+        let end = cursor.byte_offset_of(next.offset_unit, next.start_offset)?;
+        let gap = &input[start..end];
+        if gap.chars().all(char::is_whitespace) { Some(gap) } else { None }
+    }
+
+    /// Returns whether a space belongs between two terms according to the
+    /// spacing rules, where `in_quote` tracks whether a straight double quote
+    /// is currently open.
+    fn needs_space(prev: &str, next: &str, in_quote: bool) -> bool {
+        let (p, n) = match (prev.chars().next_back(), next.chars().next()) {
+            (Some(p), Some(n)) => (p, n),
+            _ => return false,
+        };
+        if is_cjk(p) && is_cjk(n) {
+            return false;
+        }
+        if CONTRACTIONS.contains(&&*next.to_lowercase()) {
+            return false;
+        }
+        if next.chars().all(|c| CLOSING.contains(c)) {
+            return false;
+        }
+        if next == "\"" || prev == "\"" {
+            return !in_quote;
+        }
+        !(prev.chars().count() == 1 && OPENING.contains(p))
+    }
+
+    /// Joins the terms of `tokens` into text.
+    pub fn detokenize(&self, tokens: &[Token]) -> String {
+        let mut cursor = OffsetCursor::new(self.input.unwrap_or(""));
+        let mut text = String::new();
+        let mut in_quote = false;
+
+        for (i, token) in tokens.iter().enumerate() {
+            if i > 0 {
+                let prev = &tokens[i - 1];
+                match self.original_gap(&mut cursor, prev, token) {
+                    Some(gap) => text.push_str(gap),
+                    None => {
+                        if Detokenizer::needs_space(prev.term(), token.term(), in_quote) {
+                            text.push(' ');
+                        }
+                    }
+                }
+            }
+            if token.term() == "\"" {
+                in_quote = !in_quote;
+            }
+            text.push_str(token.term());
+        }
+        text
+    }
+}
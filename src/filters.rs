@@ -18,8 +18,20 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::collections::HashSet;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use TokenKind;
+
 /// A type for filtering chars during tokenization.
 pub trait Filter {
     /// Returns a tuple of bool, bool indicating whether the character marks the
@@ -30,29 +42,273 @@ pub trait Filter {
     /// (true,  false) - not part of a token and should be discarded
     /// (true,   true) - not part of token but is one in its own right
     fn on_char(&self, c: &char) -> (bool, bool);
+
+    /// Classifies an extended grapheme cluster in the same way as `on_char`,
+    /// for tokenizers that operate on graphemes. Defaults to classifying the
+    /// first char of the cluster, such that a delimiter followed by combining
+    /// marks is treated as a delimiter as a whole.
+    fn on_grapheme(&self, g: &str) -> (bool, bool) {
+        match g.chars().next() {
+            Some(c) => self.on_char(&c),
+            None => (false, false),
+        }
+    }
+
+    /// Returns the scanner that matches compound tokens for this filter, or
+    /// `None` if its tokens are only ever delimited char by char, which is the
+    /// default. Since a compound token may begin anywhere before the end of
+    /// the input, iterating a filter that has a scanner in reverse tokenizes
+    /// the remaining input forwards first.
+    fn scanner(&self) -> Option<&dyn Scanner> {
+        None
+    }
+
+    /// Returns a table classifying every ASCII char exactly as `on_char` does,
+    /// allowing tokenizers to scan ASCII input a byte at a time. Filters that
+    /// have no such table return `None`, which is the default.
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        None
+    }
+}
+
+/// Matches compound tokens that `on_char` alone would split apart, such as
+/// numbers and URLs. A filter only matches compound tokens once it returns a
+/// scanner from `Filter::scanner`, usually itself.
+pub trait Scanner {
+    /// Called at the start of each potential token with the full input and the
+    /// byte index of the current char, allowing the filter to look ahead and
+    /// claim a compound token.
+    ///
+    /// Returns the length in bytes of the matched token along with its kind,
+    /// or `None` if tokenization should continue char by char. The length must
+    /// fall on a char boundary, and matches should not span line breaks, as
+    /// incremental retokenization and seeking only rescan the current line.
+    fn scan(&self, input: &str, offset: usize) -> Option<(usize, TokenKind)>;
+
+    /// Produces the term of a token matched by `scan`, which defaults to the
+    /// matched slice of the input. If the term is a narrower slice of the
+    /// match, the offsets of the token are narrowed to cover only the term,
+    /// and otherwise they cover the whole match.
+    fn term<'a>(&self, matched: &'a str, _kind: TokenKind) -> Cow<'a, str> {
+        Cow::Borrowed(matched)
+    }
+}
+
+impl<F: Filter + ?Sized> Filter for &F {
+    fn on_char(&self, c: &char) -> (bool, bool) {
+        (**self).on_char(c)
+    }
+
+    fn on_grapheme(&self, g: &str) -> (bool, bool) {
+        (**self).on_grapheme(g)
+    }
+
+    fn scanner(&self) -> Option<&dyn Scanner> {
+        (**self).scanner()
+    }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        (**self).ascii_table()
+    }
+}
+
+impl<F: Filter + ?Sized> Filter for Box<F> {
+    fn on_char(&self, c: &char) -> (bool, bool) {
+        (**self).on_char(c)
+    }
+
+    fn on_grapheme(&self, g: &str) -> (bool, bool) {
+        (**self).on_grapheme(g)
+    }
+
+    fn scanner(&self) -> Option<&dyn Scanner> {
+        (**self).scanner()
+    }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        (**self).ascii_table()
+    }
+}
+
+const WORD: u8 = 0;
+const DROP: u8 = 1;
+const KEEP: u8 = 2;
+const NON_ASCII: u8 = 3;
+
+/// A precomputed classification of the ASCII chars for a filter, indexed by
+/// byte. Bytes outside of the ASCII range are left for the filter to classify
+/// a char at a time.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AsciiTable {
+    classes: [u8; 256],
+    // For each low nibble, a bitmask of the high nibbles of the ASCII bytes
+    // that are not part of a token, used for scanning sixteen bytes at a time.
+    pub(crate) nibbles: [u8; 16],
+}
+
+impl AsciiTable {
+    /// Creates a table from the ASCII bytes that are discarded and the ASCII
+    /// bytes that are collected as tokens in their own right, any other ASCII
+    /// byte being part of a token.
+    pub const fn new(drop: &[u8], keep: &[u8]) -> AsciiTable {
+        let mut classes = [WORD; 256];
+        let mut i = 0;
+        while i < drop.len() {
+            classes[drop[i] as usize] = DROP;
+            i += 1;
+        }
+        i = 0;
+        while i < keep.len() {
+            classes[keep[i] as usize] = KEEP;
+            i += 1;
+        }
+        AsciiTable::build(classes)
+    }
+
+    /// Creates a table by classifying each ASCII char with a filter.
+    pub fn from_filter<F: Filter + ?Sized>(filter: &F) -> AsciiTable {
+        let mut classes = [WORD; 256];
+        for b in 0..128u8 {
+            classes[b as usize] = match filter.on_char(&(b as char)) {
+                (false, _) => WORD,
+                (true, false) => DROP,
+                (true, true) => KEEP,
+            };
+        }
+        AsciiTable::build(classes)
+    }
+
+    const fn build(mut classes: [u8; 256]) -> AsciiTable {
+        let mut nibbles = [0; 16];
+        let mut b = 0;
+        while b < 256 {
+            if b >= 128 {
+                classes[b] = NON_ASCII;
+            } else if classes[b] != WORD {
+                nibbles[b & 15] |= 1 << (b >> 4);
+            }
+            b += 1;
+        }
+        AsciiTable { classes, nibbles }
+    }
+
+    /// Classifies a byte in the same way as `Filter::on_char`, or returns
+    /// `None` if the byte is not ASCII.
+    #[inline]
+    pub fn class(&self, b: u8) -> Option<(bool, bool)> {
+        match self.classes[b as usize] {
+            WORD => Some((false, false)),
+            DROP => Some((true, false)),
+            KEEP => Some((true, true)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a byte is an ASCII char that is part of a token.
+    #[inline]
+    pub(crate) fn is_word(&self, b: u8) -> bool {
+        self.classes[b as usize] == WORD
+    }
+}
+
+/// A filter that wraps another filter along with a table of its classification
+/// of the ASCII chars, allowing filters without a table of their own, such as
+/// HashFilter and VecFilter, to be scanned a byte at a time over ASCII input.
+pub struct TableFilter<F: Filter = DefaultFilter> {
+    inner: F,
+    table: AsciiTable,
+}
+
+impl<F: Filter> TableFilter<F> {
+    pub fn new(inner: F) -> TableFilter<F> {
+        let table = AsciiTable::from_filter(&inner);
+        TableFilter { inner, table }
+    }
+
+    /// Returns the wrapped filter, which cannot be modified in place as the
+    /// table would no longer match it.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl TableFilter<VecFilter> {
+    /// Creates a filter that collects the chars of `keep` as tokens and
+    /// discards the chars of `drop`, any other char being part of a token.
+    pub fn from_chars(keep: &str, drop: &str) -> TableFilter<VecFilter> {
+        let mut filter = VecFilter {
+            keep_chars: Vec::new(),
+            drop_chars: Vec::new(),
+        };
+        for c in keep.chars() {
+            filter.add_keep(c);
+        }
+        for c in drop.chars() {
+            filter.add_drop(c);
+        }
+        TableFilter::new(filter)
+    }
+}
+
+impl Default for TableFilter {
+    fn default() -> TableFilter {
+        TableFilter::new(DefaultFilter)
+    }
+}
+
+impl<F: Filter> Filter for TableFilter<F> {
+    fn on_char(&self, c: &char) -> (bool, bool) {
+        if c.is_ascii() {
+            if let Some(class) = self.table.class(*c as u8) {
+                return class;
+            }
+        }
+        self.inner.on_char(c)
+    }
+
+    fn on_grapheme(&self, g: &str) -> (bool, bool) {
+        self.inner.on_grapheme(g)
+    }
+
+    fn scanner(&self) -> Option<&dyn Scanner> {
+        self.inner.scanner()
+    }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        Some(&self.table)
+    }
 }
 
 /// A filter for selecting whitespace characters only.
 pub struct WhitespaceFilter;
 
+static WHITESPACE_TABLE: AsciiTable = AsciiTable::new(b"\t\n\x0b\x0c\r ", b"");
+
 impl Filter for WhitespaceFilter {
     fn on_char(&self, c: &char) -> (bool, bool) {
         (c.is_whitespace(), false)
     }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        Some(&WHITESPACE_TABLE)
+    }
 }
 
 /// A filter for extracting characters based on two hashmaps, one for keep chars
 /// which are collected as tokens, and one for drop chars which will be removed
 /// entirely.
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct HashFilter {
     pub keep_chars: HashSet<char>,
     pub drop_chars: HashSet<char>,
 }
 
+#[cfg(feature = "std")]
 impl Filter for HashFilter {
     fn on_char(&self, c: &char) -> (bool, bool) {
-        let is_keep = self.keep_chars.contains(&c);
-        (is_keep || self.drop_chars.contains(&c), is_keep)
+        let is_keep = self.keep_chars.contains(c);
+        (is_keep || self.drop_chars.contains(c), is_keep)
     }
 }
 
@@ -60,6 +316,7 @@ impl Filter for HashFilter {
 /// characters by index. This means each vector will have a length equal to or
 /// greater than the largest character, and lookups can be much faster than a
 /// HashSet since we can prefilter on vec length.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct VecFilter {
     pub keep_chars: Vec<bool>,
     pub drop_chars: Vec<bool>,
@@ -109,4 +366,671 @@ impl Filter for DefaultFilter {
             _ => (false, false),
         }
     }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        Some(&DEFAULT_TABLE)
+    }
+}
+
+static DEFAULT_TABLE: AsciiTable = AsciiTable::new(
+    b" \t\n\r\x0c",
+    b"#!\\\"%&'()*+-./:;<=>?@[]^_`{|}~",
+);
+
+/// A filter that wraps another filter and extracts numeric literals as single
+/// tokens of kind `TokenKind::Number`, including decimals (`0.4`), version
+/// strings (`1.2.3`), signed numbers (`-7`), percentages (`50%`), thousands
+/// separators (`1,000,000`) and scientific notation (`6.02e23`).
+///
+/// Numbers immediately followed by a letter, such as `5th`, are left to the
+/// wrapped filter.
+pub struct NumberFilter<F: Filter = DefaultFilter> {
+    pub inner: F,
+}
+
+impl<F: Filter> NumberFilter<F> {
+    pub fn new(inner: F) -> Self {
+        NumberFilter { inner }
+    }
+}
+
+impl Default for NumberFilter {
+    fn default() -> Self {
+        NumberFilter::new(DefaultFilter {})
+    }
+}
+
+impl<F: Filter> Filter for NumberFilter<F> {
+    fn on_char(&self, c: &char) -> (bool, bool) {
+        self.inner.on_char(c)
+    }
+
+    fn on_grapheme(&self, g: &str) -> (bool, bool) {
+        self.inner.on_grapheme(g)
+    }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        self.inner.ascii_table()
+    }
+
+    fn scanner(&self) -> Option<&dyn Scanner> {
+        Some(self)
+    }
+}
+
+impl<F: Filter> Scanner for NumberFilter<F> {
+    fn scan(&self, input: &str, offset: usize) -> Option<(usize, TokenKind)> {
+        match scan_number(input, offset) {
+            Some(len) => Some((len, TokenKind::Number)),
+            None => self.inner.scanner()?.scan(input, offset),
+        }
+    }
+
+    fn term<'a>(&self, matched: &'a str, kind: TokenKind) -> Cow<'a, str> {
+        self.inner.scanner().map_or(Cow::Borrowed(matched), |s| s.term(matched, kind))
+    }
+}
+
+fn count_digits(bytes: &[u8], from: usize) -> usize {
+    bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count()
+}
+
+/// Returns the length in bytes of a numeric literal starting at `offset`.
+fn scan_number(input: &str, offset: usize) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let mut i = offset;
+
+    // A sign only counts when it isn't joining two words, e.g. `1-2` or `a-1`.
+    if i < bytes.len() && (bytes[i] == b'-' || bytes[i] == b'+') {
+        match input[..offset].chars().next_back() {
+            Some(p) if p.is_alphanumeric() => return None,
+            _ => i += 1,
+        }
+    }
+
+    let int_len = count_digits(bytes, i);
+    if int_len == 0 {
+        return None;
+    }
+    i += int_len;
+
+    // Thousands separators must be followed by exactly three digits.
+    while i < bytes.len() && bytes[i] == b',' && count_digits(bytes, i + 1) == 3 {
+        i += 4;
+    }
+
+    // Decimals, or any number of dot separated parts for versions.
+    while i < bytes.len() && bytes[i] == b'.' && count_digits(bytes, i + 1) > 0 {
+        i += 1 + count_digits(bytes, i + 1);
+    }
+
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        let mut j = i + 1;
+        if j < bytes.len() && (bytes[j] == b'-' || bytes[j] == b'+') {
+            j += 1;
+        }
+        let exp_len = count_digits(bytes, j);
+        if exp_len > 0 {
+            i = j + exp_len;
+        }
+    }
+
+    if i < bytes.len() && bytes[i] == b'%' {
+        i += 1;
+    }
+
+    match input[i..].chars().next() {
+        Some(c) if c.is_alphanumeric() => None,
+        _ => Some(i - offset),
+    }
+}
+
+/// A filter that wraps another filter and extracts URLs, email addresses,
+/// IPv4/IPv6 addresses and optionally hostnames as single tokens of kind
+/// `TokenKind::Url`, `TokenKind::Email`, `TokenKind::IpAddress` and
+/// `TokenKind::Hostname` respectively.
+///
+/// Since IP addresses would otherwise look like version strings this filter
+/// should wrap a `NumberFilter` rather than the other way around. Components
+/// of the extracted tokens can be emitted with
+/// `token_filters::UrlComponentFilter`.
+pub struct UrlFilter<F: Filter = DefaultFilter> {
+    pub inner: F,
+
+    /// Whether to extract bare domain names such as `example.com`, which is
+    /// disabled by default as file names and field accesses such as `main.rs`
+    /// or `self.value` look the same.
+    pub hostnames: bool,
+}
+
+impl<F: Filter> UrlFilter<F> {
+    pub fn new(inner: F) -> Self {
+        UrlFilter {
+            inner,
+            hostnames: false,
+        }
+    }
+}
+
+impl Default for UrlFilter {
+    fn default() -> Self {
+        UrlFilter::new(DefaultFilter {})
+    }
+}
+
+impl<F: Filter> Filter for UrlFilter<F> {
+    fn on_char(&self, c: &char) -> (bool, bool) {
+        self.inner.on_char(c)
+    }
+
+    fn on_grapheme(&self, g: &str) -> (bool, bool) {
+        self.inner.on_grapheme(g)
+    }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        self.inner.ascii_table()
+    }
+
+    fn scanner(&self) -> Option<&dyn Scanner> {
+        Some(self)
+    }
+}
+
+impl<F: Filter> Scanner for UrlFilter<F> {
+    fn scan(&self, input: &str, offset: usize) -> Option<(usize, TokenKind)> {
+        if let Some(len) = scan_url(input, offset) {
+            return Some((len, TokenKind::Url));
+        }
+        if let Some(len) = scan_email(input, offset) {
+            return Some((len, TokenKind::Email));
+        }
+        if let Some(len) = scan_ipv6(input, offset).or_else(|| scan_ipv4(input, offset)) {
+            return Some((len, TokenKind::IpAddress));
+        }
+        if let Some(len) = scan_hostname(input, offset).filter(|_| self.hostnames) {
+            return Some((len, TokenKind::Hostname));
+        }
+        self.inner.scanner()?.scan(input, offset)
+    }
+
+    fn term<'a>(&self, matched: &'a str, kind: TokenKind) -> Cow<'a, str> {
+        self.inner.scanner().map_or(Cow::Borrowed(matched), |s| s.term(matched, kind))
+    }
+}
+
+fn ends_word(input: &str, end: usize) -> bool {
+    match input[end..].chars().next() {
+        Some(c) => !c.is_alphanumeric() && c != '_',
+        None => true,
+    }
+}
+
+/// Returns the length in bytes of a URL with a scheme starting at `offset`.
+pub(crate) fn scan_url(input: &str, offset: usize) -> Option<usize> {
+    let rest = &input[offset..];
+    if !rest.bytes().next()?.is_ascii_alphabetic() {
+        return None;
+    }
+
+    let scheme_len = rest
+        .bytes()
+        .take_while(|b| b.is_ascii_alphanumeric() || *b == b'+' || *b == b'.' || *b == b'-')
+        .count();
+    if !rest[scheme_len..].starts_with("://") {
+        return None;
+    }
+
+    let body_start = scheme_len + 3;
+    let mut end = rest[body_start..]
+        .find(|c: char| {
+            c.is_whitespace() || c == '<' || c == '>' || c == '"' || c == '\u{201C}' ||
+                c == '\u{201D}'
+        })
+        .map_or(rest.len(), |i| body_start + i);
+
+    // Trailing punctuation most likely belongs to the surrounding text.
+    while end > body_start {
+        let url = &rest[body_start..end];
+        let unbalanced = |open: char, close: char| {
+            url.matches(close).count() > url.matches(open).count()
+        };
+        match url.as_bytes()[url.len() - 1] {
+            b'.' | b',' | b';' | b':' | b'!' | b'?' | b'\'' => end -= 1,
+            b')' if unbalanced('(', ')') => end -= 1,
+            b']' if unbalanced('[', ']') => end -= 1,
+            _ => break,
+        }
+    }
+
+    if end == body_start { None } else { Some(end) }
+}
+
+/// Returns the length in bytes of an email address starting at `offset`.
+pub(crate) fn scan_email(input: &str, offset: usize) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let local_len = bytes[offset..]
+        .iter()
+        .take_while(|b| {
+            b.is_ascii_alphanumeric() || b"._%+-".contains(b)
+        })
+        .count();
+    if local_len == 0 || bytes[offset] == b'.' || bytes.get(offset + local_len) != Some(&b'@') {
+        return None;
+    }
+    let domain_start = offset + local_len + 1;
+    scan_hostname(input, domain_start).map(|len| domain_start + len - offset)
+}
+
+/// Returns the length in bytes of a domain name with at least two labels and
+/// an alphabetic top level domain starting at `offset`.
+pub(crate) fn scan_hostname(input: &str, offset: usize) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let mut end = offset;
+    let mut labels = 0;
+    let mut tld = (offset, offset);
+
+    loop {
+        let start = if labels == 0 { offset } else { end + 1 };
+        let len = bytes[start..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || **b == b'-')
+            .count();
+        if len == 0 || bytes[start] == b'-' || bytes[start + len - 1] == b'-' {
+            break;
+        }
+        labels += 1;
+        end = start + len;
+        tld = (start, end);
+        if bytes.get(end) != Some(&b'.') {
+            break;
+        }
+    }
+
+    let tld = &bytes[tld.0..tld.1];
+    if labels < 2 || tld.len() < 2 || !tld.iter().all(|b| b.is_ascii_alphabetic()) ||
+        !ends_word(input, end) || bytes.get(end) == Some(&b'-')
+    {
+        return None;
+    }
+    Some(end - offset)
+}
+
+/// Returns the length in bytes of a dotted decimal IPv4 address starting at
+/// `offset`.
+pub(crate) fn scan_ipv4(input: &str, offset: usize) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let mut i = offset;
+    for octet in 0..4 {
+        if octet > 0 {
+            if bytes.get(i) != Some(&b'.') {
+                return None;
+            }
+            i += 1;
+        }
+        let len = count_digits(bytes, i);
+        if len == 0 || len > 3 || input[i..i + len].parse::<u16>().ok()? > 255 {
+            return None;
+        }
+        i += len;
+    }
+    if !ends_word(input, i) || (bytes.get(i) == Some(&b'.') && count_digits(bytes, i + 1) > 0) {
+        return None;
+    }
+    Some(i - offset)
+}
+
+/// Returns the length in bytes of an IPv6 address in hex group notation
+/// starting at `offset`. Compressed addresses must contain a decimal digit, so
+/// that paths such as `a::b` are not mistaken for one.
+pub(crate) fn scan_ipv6(input: &str, offset: usize) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let len = bytes[offset..]
+        .iter()
+        .take_while(|b| b.is_ascii_hexdigit() || **b == b':')
+        .count();
+    let addr = &input[offset..offset + len];
+    if len == 0 || !ends_word(input, offset + len) || addr.matches(':').count() < 2 {
+        return None;
+    }
+
+    let compressed = addr.matches("::").count();
+    if compressed > 1 || addr.contains(":::") {
+        return None;
+    }
+    let groups = addr.split(':').filter(|g| !g.is_empty());
+    let mut count = 0;
+    for group in groups {
+        if group.len() > 4 {
+            return None;
+        }
+        count += 1;
+    }
+    let (head, tail) = (addr.starts_with(':'), addr.ends_with(':'));
+    if (head && !addr.starts_with("::")) || (tail && !addr.ends_with("::")) {
+        return None;
+    }
+    if (compressed == 0 && count != 8) || (compressed == 1 && count > 7) {
+        return None;
+    }
+    if compressed == 1 && (count == 0 || !addr.bytes().any(|b| b.is_ascii_digit())) {
+        return None;
+    }
+    Some(len)
+}
+
+/// A filter that wraps another filter and extracts hashtags, `@` mentions and
+/// emoji as single tokens of kind `TokenKind::Hashtag`, `TokenKind::Mention`
+/// and `TokenKind::Emoji` respectively.
+///
+/// Emoji are treated as delimiters by this filter so that they are separated
+/// from any adjacent words, but multi code point sequences such as `👩‍💻`,
+/// `👋🏽` or `🇬🇧` are never split apart.
+pub struct SocialFilter<F: Filter = DefaultFilter> {
+    pub inner: F,
+}
+
+impl<F: Filter> SocialFilter<F> {
+    pub fn new(inner: F) -> Self {
+        SocialFilter { inner }
+    }
+}
+
+impl Default for SocialFilter {
+    fn default() -> Self {
+        SocialFilter::new(DefaultFilter {})
+    }
+}
+
+impl<F: Filter> Filter for SocialFilter<F> {
+    fn on_char(&self, c: &char) -> (bool, bool) {
+        if is_pictographic(*c) || is_regional_indicator(*c) {
+            return (true, true);
+        }
+        self.inner.on_char(c)
+    }
+
+    fn on_grapheme(&self, g: &str) -> (bool, bool) {
+        match g.chars().next() {
+            Some(c) if is_pictographic(c) || is_regional_indicator(c) => (true, true),
+            _ => self.inner.on_grapheme(g),
+        }
+    }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        self.inner.ascii_table()
+    }
+
+    fn scanner(&self) -> Option<&dyn Scanner> {
+        Some(self)
+    }
+}
+
+impl<F: Filter> Scanner for SocialFilter<F> {
+    fn scan(&self, input: &str, offset: usize) -> Option<(usize, TokenKind)> {
+        let tag = match *input.as_bytes().get(offset)? {
+            b'#' => Some(TokenKind::Hashtag),
+            b'@' => Some(TokenKind::Mention),
+            _ => None,
+        };
+        if let Some(kind) = tag {
+            if let Some(len) = scan_tag(input, offset) {
+                return Some((len, kind));
+            }
+        }
+        if let Some(len) = scan_emoji(input, offset) {
+            return Some((len, TokenKind::Emoji));
+        }
+        self.inner.scanner()?.scan(input, offset)
+    }
+
+    fn term<'a>(&self, matched: &'a str, kind: TokenKind) -> Cow<'a, str> {
+        self.inner.scanner().map_or(Cow::Borrowed(matched), |s| s.term(matched, kind))
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Returns the length in bytes of a `#` or `@` prefixed tag starting at
+/// `offset`, which must contain at least one letter and must not directly
+/// follow a word, as in `C#` or `ash@jeffs.eu`.
+fn scan_tag(input: &str, offset: usize) -> Option<usize> {
+    if input[..offset].chars().next_back().is_some_and(is_word_char) {
+        return None;
+    }
+    let body = &input[offset + 1..];
+    let len = body.find(|c| !is_word_char(c)).unwrap_or(body.len());
+    if !body[..len].chars().any(char::is_alphabetic) {
+        return None;
+    }
+    Some(1 + len)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+fn is_skin_tone(c: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+}
+
+fn is_tag_char(c: char) -> bool {
+    ('\u{E0020}'..='\u{E007F}').contains(&c)
+}
+
+/// Approximates the Unicode Extended_Pictographic property.
+fn is_pictographic(c: char) -> bool {
+    match c as u32 {
+        0xA9 | 0xAE | 0x203C | 0x2049 | 0x2122 | 0x2139 | 0x2194..=0x2199 | 0x21A9 |
+        0x21AA | 0x231A | 0x231B | 0x2328 | 0x23CF | 0x23E9..=0x23F3 | 0x23F8..=0x23FA |
+        0x24C2 | 0x25AA | 0x25AB | 0x25B6 | 0x25C0 | 0x25FB..=0x25FE | 0x2600..=0x27BF |
+        0x2934 | 0x2935 | 0x2B05..=0x2B07 | 0x2B1B | 0x2B1C | 0x2B50 | 0x2B55 | 0x3030 |
+        0x303D | 0x3297 | 0x3299 => true,
+        0x1F000..=0x1FAFF => !is_regional_indicator(c) && !is_skin_tone(c),
+        _ => false,
+    }
+}
+
+/// Returns the length in bytes of an emoji sequence starting at `offset`,
+/// covering flags, keycaps, skin tone modifiers, tag sequences and zero width
+/// joiner sequences.
+fn scan_emoji(input: &str, offset: usize) -> Option<usize> {
+    let rest = &input[offset..];
+    let mut chars = rest.char_indices();
+    let (_, first) = chars.next()?;
+
+    // Flags are pairs of regional indicators.
+    if is_regional_indicator(first) {
+        return match chars.next() {
+            Some((i, c)) if is_regional_indicator(c) => Some(i + c.len_utf8()),
+            _ => Some(first.len_utf8()),
+        };
+    }
+
+    // Keycaps such as 1️⃣ are an ASCII char, an optional variation selector and
+    // an enclosing keycap.
+    if first.is_ascii_digit() || first == '#' || first == '*' {
+        let mut keycap = rest[1..].chars();
+        let mut next = keycap.next();
+        let mut len = 1;
+        if next == Some('\u{FE0F}') {
+            next = keycap.next();
+            len += '\u{FE0F}'.len_utf8();
+        }
+        return if next == Some('\u{20E3}') {
+            Some(len + '\u{20E3}'.len_utf8())
+        } else {
+            None
+        };
+    }
+
+    if !is_pictographic(first) {
+        return None;
+    }
+    let mut end = first.len_utf8();
+    loop {
+        let mut tail = rest[end..].chars();
+        match tail.next() {
+            Some(c) if c == '\u{FE0F}' || is_skin_tone(c) || is_tag_char(c) => {
+                end += c.len_utf8();
+            }
+            Some('\u{200D}') => match tail.next() {
+                Some(n) if is_pictographic(n) => end += '\u{200D}'.len_utf8() + n.len_utf8(),
+                _ => break,
+            },
+            _ => break,
+        }
+    }
+    Some(end)
+}
+
+/// A filter that wraps another filter and extracts quoted strings as single
+/// tokens of kind `TokenKind::Quoted`, such that `"hello world"` and `'it\'s'`
+/// are each one token.
+///
+/// An opening quote directly following a word, as in `it's`, is left to the
+/// wrapped filter, as is a `'` that is not closed on its line, as in `'tis`.
+/// Quotes do not span lines, and any other quote that is not closed before
+/// the end of its line is extracted up to the line break as
+/// `TokenKind::UnterminatedQuote`. A closing quote directly followed by a
+/// word, as in `'don't'`, does not close the quote.
+///
+/// When quotes are stripped, token offsets cover only the text between them,
+/// unless escapes were resolved, in which case they cover the quotes too.
+pub struct QuoteFilter<F: Filter = DefaultFilter> {
+    pub inner: F,
+
+    /// Pairs of opening and closing quote chars.
+    pub quotes: Vec<(char, char)>,
+
+    /// Whether a backslash prevents the char following it from closing a
+    /// quote.
+    pub escapes: bool,
+
+    /// Whether to remove the quotes from the extracted term.
+    pub strip_quotes: bool,
+
+    /// Whether to resolve backslash escapes in the extracted term.
+    pub unescape: bool,
+}
+
+impl<F: Filter> QuoteFilter<F> {
+    pub fn new(inner: F) -> Self {
+        QuoteFilter {
+            inner,
+            quotes: [('"', '"'), ('\'', '\''), ('\u{201C}', '\u{201D}')].to_vec(),
+            escapes: true,
+            strip_quotes: false,
+            unescape: false,
+        }
+    }
+}
+
+impl Default for QuoteFilter {
+    fn default() -> Self {
+        QuoteFilter::new(DefaultFilter {})
+    }
+}
+
+impl<F: Filter> Filter for QuoteFilter<F> {
+    fn on_char(&self, c: &char) -> (bool, bool) {
+        self.inner.on_char(c)
+    }
+
+    fn on_grapheme(&self, g: &str) -> (bool, bool) {
+        self.inner.on_grapheme(g)
+    }
+
+    fn ascii_table(&self) -> Option<&AsciiTable> {
+        self.inner.ascii_table()
+    }
+
+    fn scanner(&self) -> Option<&dyn Scanner> {
+        Some(self)
+    }
+}
+
+impl<F: Filter> Scanner for QuoteFilter<F> {
+    fn scan(&self, input: &str, offset: usize) -> Option<(usize, TokenKind)> {
+        let rest = &input[offset..];
+        let open = rest.chars().next()?;
+        let close = self.quotes.iter().find(|q| q.0 == open).map(|q| q.1);
+        // The grapheme preceding the quote is checked as a whole, such that a
+        // word ending in a combining mark still counts as a word.
+        let after_word = close.is_some() &&
+            input[..offset]
+                .graphemes(true)
+                .next_back()
+                .and_then(|g| g.chars().next())
+                .is_some_and(is_word_char);
+
+        if let (Some(close), false) = (close, after_word) {
+            let body_start = open.len_utf8();
+            let mut escaped = false;
+            let mut line_end = rest.len();
+            for (i, c) in rest[body_start..].char_indices() {
+                if c == '\n' || c == '\r' {
+                    line_end = body_start + i;
+                    break;
+                }
+                if escaped {
+                    escaped = false;
+                } else if self.escapes && c == '\\' {
+                    escaped = true;
+                } else if c == close {
+                    let end = body_start + i + c.len_utf8();
+                    if !rest[end..].chars().next().is_some_and(is_word_char) {
+                        return Some((end, TokenKind::Quoted));
+                    }
+                }
+            }
+            // An apostrophe that is never closed is more likely an elision
+            // than the start of a quote.
+            if open != '\'' {
+                return Some((line_end, TokenKind::UnterminatedQuote));
+            }
+        }
+        self.inner.scanner()?.scan(input, offset)
+    }
+
+    fn term<'a>(&self, matched: &'a str, kind: TokenKind) -> Cow<'a, str> {
+        let mut content = matched;
+        match kind {
+            TokenKind::Quoted | TokenKind::UnterminatedQuote if self.strip_quotes => {
+                let mut chars = content.chars();
+                chars.next();
+                if kind == TokenKind::Quoted {
+                    chars.next_back();
+                }
+                content = chars.as_str();
+            }
+            TokenKind::Quoted | TokenKind::UnterminatedQuote => {}
+            _ => {
+                let inner = self.inner.scanner();
+                return inner.map_or(Cow::Borrowed(matched), |s| s.term(matched, kind));
+            }
+        }
+
+        if !self.unescape || !content.contains('\\') {
+            return Cow::Borrowed(content);
+        }
+        let mut unescaped = String::with_capacity(content.len());
+        let mut chars = content.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some('r') => unescaped.push('\r'),
+                Some('0') => unescaped.push('\0'),
+                Some(e) => unescaped.push(e),
+                None => unescaped.push('\\'),
+            }
+        }
+        Cow::Owned(unescaped)
+    }
 }
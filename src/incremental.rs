@@ -0,0 +1,208 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use filters::Filter;
+use {line_start, FilteredTokenizer, LineColumn, Token};
+
+/// An edit that replaced a range of bytes of an input with new text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit {
+    /// The offset in bytes of the start of the edit.
+    pub start: usize,
+
+    /// The offset in bytes of the end of the replaced text in the old input.
+    pub old_end: usize,
+
+    /// The offset in bytes of the end of the inserted text in the new input.
+    pub new_end: usize,
+}
+
+/// The tokens of an edited input along with which of them changed.
+pub struct Retokenized<'a> {
+    /// The tokens of the new input.
+    pub tokens: Vec<Token<'a>>,
+
+    /// The indexes of the previous tokens that were replaced.
+    pub removed: Range<usize>,
+
+    /// The indexes of `tokens` that replaced them.
+    pub inserted: Range<usize>,
+}
+
+fn shift(value: usize, delta: isize) -> usize {
+    (value as isize + delta) as usize
+}
+
+/// Retokenizes an input after an edit, given the tokens of the input before
+/// the edit, without retokenizing any more of it than necessary.
+///
+/// Tokenization resumes from the token preceding the edit, or from the start
+/// of its line for filters with a scanner, and stops as soon as a token is
+/// produced after the edit that matches a previous token, after which the
+/// remaining previous tokens are reused with their offsets and positions
+/// shifted. The tokenizer returned by `tokenizer` must be configured in the
+/// same way as the one that produced `previous`.
+pub fn retokenize<'a, T, F>(
+    previous: &[Token],
+    input: &'a str,
+    edit: Edit,
+    tokenizer: F,
+) -> Retokenized<'a>
+where
+    T: Filter,
+    F: FnOnce(&'a str) -> FilteredTokenizer<'a, T>,
+{
+    let byte_delta = edit.new_end as isize - edit.old_end as isize;
+    let rebase = |token: &Token, delta: isize| -> Cow<'a, str> {
+        match token.term {
+            Cow::Borrowed(_) => {
+                Cow::Borrowed(&input[shift(token.start_byte, delta)..shift(token.end_byte, delta)])
+            }
+            Cow::Owned(ref term) => Cow::Owned(term.clone()),
+        }
+    };
+
+    let mut tokenizer = tokenizer(input);
+
+    // An edit directly following a token may extend it, so resume from the
+    // first token that reaches the start of the edit. If that token starts
+    // after the edit, its offsets no longer hold, so resume from the token
+    // before it instead, or from the start of the input if there is none.
+    // By grapheme, an edit may also join the grapheme preceding it, so the
+    // token must start before the edit. A filter with a scanner may have
+    // matched the tokens preceding the edit differently had the edit been
+    // there, such as `3.` before `14`, so for those resume from the start of
+    // the line.
+    let graphemes = tokenizer.graphemes;
+    let resumable =
+        |t: &Token| t.start_byte < edit.start || (!graphemes && t.start_byte == edit.start);
+    let from = if tokenizer.filter.scanner().is_some() {
+        line_start(input, edit.start)
+    } else {
+        edit.start
+    };
+    let mut first = previous
+        .iter()
+        .position(|t| t.end_byte >= from)
+        .unwrap_or(previous.len());
+    if previous.get(first).is_some_and(|t| !resumable(t)) {
+        first = first.saturating_sub(1);
+    }
+
+    let mut tokens: Vec<Token<'a>> = previous[..first]
+        .iter()
+        .map(|t| Token {
+            term: rebase(t, 0),
+            ..*t
+        })
+        .collect();
+
+    match previous.get(first) {
+        Some(token) if resumable(token) => tokenizer.resume(token),
+        Some(_) => {}
+        None => {
+            if let Some(token) = previous.last() {
+                tokenizer.resume(token);
+                tokenizer.next();
+            }
+        }
+    }
+
+    let mut candidate = previous
+        .iter()
+        .position(|t| t.start_byte >= edit.old_end)
+        .unwrap_or(previous.len());
+    let mut resync = None;
+    for token in tokenizer {
+        if token.start_byte >= edit.new_end {
+            while candidate < previous.len() &&
+                shift(previous[candidate].start_byte, byte_delta) < token.start_byte
+            {
+                candidate += 1;
+            }
+            if let Some(old) = previous.get(candidate) {
+                // Columns following a tab depend on the tab stops, so tokens
+                // on the same line are only reused if their columns are
+                // unchanged or no tab follows.
+                let line = &input[token.start_byte..];
+                let line = &line[..line.find(['\n', '\r']).unwrap_or(line.len())];
+                if shift(old.start_byte, byte_delta) == token.start_byte &&
+                    shift(old.end_byte, byte_delta) == token.end_byte &&
+                    old.kind == token.kind &&
+                    (old.location.map(|l| l.column) == token.location.map(|l| l.column) ||
+                        !line.contains('\t'))
+                {
+                    resync = Some(token);
+                    break;
+                }
+            }
+        }
+        tokens.push(token);
+    }
+
+    let inserted = first..tokens.len();
+    let resync = match resync {
+        Some(token) => token,
+        None => {
+            return Retokenized {
+                tokens,
+                removed: first..previous.len(),
+                inserted,
+            };
+        }
+    };
+
+    let old = &previous[candidate];
+    let offset_delta = resync.start_offset as isize - old.start_offset as isize;
+    let position_delta = resync.position as isize - old.position as isize;
+    for token in &previous[candidate..] {
+        let location = match (token.location, old.location, resync.location) {
+            (Some(l), Some(from), Some(to)) => Some(LineColumn {
+                line: shift(l.line, to.line as isize - from.line as isize),
+                column: if l.line == from.line {
+                    shift(l.column, to.column as isize - from.column as isize)
+                } else {
+                    l.column
+                },
+            }),
+            _ => None,
+        };
+        tokens.push(Token {
+            term: rebase(token, byte_delta),
+            start_offset: shift(token.start_offset, offset_delta),
+            end_offset: shift(token.end_offset, offset_delta),
+            start_byte: shift(token.start_byte, byte_delta),
+            end_byte: shift(token.end_byte, byte_delta),
+            position: shift(token.position, position_delta),
+            location,
+            ..*token
+        });
+    }
+
+    Retokenized {
+        tokens,
+        removed: first..candidate,
+        inserted,
+    }
+}
@@ -61,38 +61,328 @@
 //!
 //! assert_eq!(tokens.get(0).unwrap().term(), "hello!world");
 //! ```
+//!
+//! # Matching compound tokens
+//!
+//! Filters can also claim tokens spanning several chars by returning a
+//! scanner, which is tried at the start of each potential token.
+//!
+//! ```
+//! use tokesies::*;
+//! use tokesies::filters::{DefaultFilter, Filter, Scanner};
+//!
+//! pub struct ArrowFilter;
+//!
+//! impl Filter for ArrowFilter {
+//!     fn on_char(&self, c: &char) -> (bool, bool) {
+//!         DefaultFilter.on_char(c)
+//!     }
+//!
+//!     fn scanner(&self) -> Option<&dyn Scanner> {
+//!         Some(self)
+//!     }
+//! }
+//!
+//! impl Scanner for ArrowFilter {
+//!     fn scan(&self, input: &str, offset: usize) -> Option<(usize, TokenKind)> {
+//!         if input[offset..].starts_with("->") {
+//!             return Some((2, TokenKind::Punctuation));
+//!         }
+//!         None
+//!     }
+//! }
+//!
+//! let line = "a -> b";
+//! let tokens = FilteredTokenizer::new(ArrowFilter, line).collect::<Vec<Token>>();
+//!
+//! // tokens: ["a", "->", "b"]
+//!
+//! assert_eq!(tokens.get(1).unwrap().term(), "->");
+//! ```
+//!
+//! # Without std
+//!
+//! The `std` feature is enabled by default. Without it the crate depends only
+//! on `core` and `alloc`, in which case `HashFilter`, the `sentences` module
+//! and the `parallel` module are unavailable.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "tantivy")]
+extern crate tantivy;
+extern crate unicode_segmentation;
 
+mod ascii;
+#[cfg(feature = "std")]
 mod test;
 
+#[cfg(feature = "std")]
+pub mod bpe;
+pub mod bytes;
+#[cfg(feature = "serde")]
+pub mod config;
+pub mod detokenizer;
 pub mod filters;
+pub mod incremental;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod sentences;
+#[cfg(feature = "std")]
+pub mod subword;
+#[cfg(feature = "tantivy")]
+pub mod tantivy_tokenizer;
+pub mod token_filters;
+#[cfg(feature = "std")]
+pub mod unigram;
+#[cfg(feature = "std")]
+pub mod wordpiece;
+
+use core::fmt;
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
-use std::fmt;
-use std::borrow::Cow;
+/// Describes the type of content a token was extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// A run of characters that were not selected by the filter.
+    Word,
+
+    /// A single character selected by the filter as a token in its own right.
+    Punctuation,
+
+    /// A numeric literal such as `42`, `-0.4`, `1,000`, `1.2.3`, `50%` or
+    /// `6.02e23`.
+    Number,
+
+    /// A URL with a scheme, such as `https://example.com/a?b=c`.
+    Url,
+
+    /// An email address such as `ash@jeffs.eu`.
+    Email,
+
+    /// An IPv4 or IPv6 address.
+    IpAddress,
+
+    /// A domain name such as `example.com`.
+    Hostname,
+
+    /// A hashtag such as `#rustlang`.
+    Hashtag,
+
+    /// A user mention such as `@jeffail`.
+    Mention,
+
+    /// An emoji, including sequences joined with zero width joiners, skin tone
+    /// modifiers, keycaps and flags.
+    Emoji,
+
+    /// A quoted string such as `"hello world"`.
+    Quoted,
+
+    /// A quoted string that was still open at the end of its line.
+    UnterminatedQuote,
+
+    /// A run of chars discarded by the filter, only emitted by lossless
+    /// tokenizers.
+    Trivia,
+
+    /// A sequence of bytes that is not valid UTF-8, only emitted by byte
+    /// tokenizers.
+    InvalidUtf8,
+}
+
+/// The unit in which token offsets are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OffsetUnit {
+    /// UTF-8 code units, suitable for slicing the input directly.
+    Bytes,
+
+    /// Unicode scalar values, as iterated by `str::chars`.
+    Chars,
+
+    /// UTF-16 code units, as used by JavaScript strings and the Language
+    /// Server Protocol.
+    Utf16,
+
+    /// Extended grapheme clusters.
+    Graphemes,
+}
+
+impl OffsetUnit {
+    /// Returns the length of a string in this unit.
+    pub fn measure(&self, s: &str) -> usize {
+        match *self {
+            OffsetUnit::Bytes => s.len(),
+            OffsetUnit::Chars => s.chars().count(),
+            OffsetUnit::Utf16 => s.chars().map(char::len_utf16).sum(),
+            OffsetUnit::Graphemes => s.graphemes(true).count(),
+        }
+    }
+}
+
+/// A line and column within an input, both starting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Tracks the line and column of a tokenizer as it advances.
+struct LineTracker {
+    tab_width: usize,
+    location: LineColumn,
+    after_cr: bool,
+}
+
+impl LineTracker {
+    fn new(tab_width: usize) -> Self {
+        LineTracker {
+            tab_width: tab_width.max(1),
+            location: LineColumn { line: 1, column: 1 },
+            after_cr: false,
+        }
+    }
+
+    /// Advances past a slice of input, where columns are counted in `unit`.
+    /// A `\r\n` pair is counted as a single line break even when split
+    /// across slices.
+    fn advance(&mut self, slice: &str, unit: OffsetUnit) {
+        let mut rest = slice;
+        while let Some(i) = rest.find(['\n', '\r', '\t']) {
+            if i > 0 {
+                self.location.column += unit.measure(&rest[..i]);
+                self.after_cr = false;
+            }
+            match rest.as_bytes()[i] {
+                b'\n' => {
+                    if !self.after_cr {
+                        self.location.line += 1;
+                    }
+                    self.location.column = 1;
+                    self.after_cr = false;
+                }
+                b'\r' => {
+                    self.location.line += 1;
+                    self.location.column = 1;
+                    self.after_cr = true;
+                }
+                _ => {
+                    let stop = (self.location.column - 1) % self.tab_width;
+                    self.location.column += self.tab_width - stop;
+                    self.after_cr = false;
+                }
+            }
+            rest = &rest[i + 1..];
+        }
+        if !rest.is_empty() {
+            self.location.column += unit.measure(rest);
+            self.after_cr = false;
+        }
+    }
+}
+
+/// Returns the offset in bytes of the start of the line containing the byte
+/// offset `offset`.
+pub(crate) fn line_start(input: &str, offset: usize) -> usize {
+    input[..offset].rfind(['\n', '\r']).map_or(0, |i| i + 1)
+}
 
 /// Contains context for a token extracted from an input.
+///
+/// With the `serde` feature tokens can be serialized, and deserializing a
+/// token borrows its term from the serialized input where possible.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Token<'a> {
     /// The content of the extracted token.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub term: Cow<'a, str>,
 
-    /// The absolute offset of the token in chars.
+    /// The absolute offset of the start of the token, in chars unless the
+    /// tokenizer was configured with a different `OffsetUnit`.
     pub start_offset: usize,
 
+    /// The absolute offset of the end of the token, exclusive, in the same
+    /// unit as `start_offset`.
+    pub end_offset: usize,
+
+    /// The unit of `start_offset` and `end_offset`.
+    pub offset_unit: OffsetUnit,
+
+    /// The absolute offset of the start of the token in bytes.
+    pub start_byte: usize,
+
+    /// The absolute offset of the end of the token in bytes, exclusive.
+    pub end_byte: usize,
+
     /// The token position.
     pub position: usize,
+
+    /// The type of content the token was extracted from.
+    pub kind: TokenKind,
+
+    /// The line and column of the start of the token, if the tokenizer was
+    /// configured to track lines.
+    pub location: Option<LineColumn>,
 }
 
 impl<'a> Token<'a> {
+    /// Creates a token of kind `TokenKind::Word` for an input consisting of
+    /// only `term`, preceded by `start_offset` single byte chars.
     pub fn from_str(term: &'a str, start_offset: usize, position: usize) -> Self {
         Token {
             term: Cow::Borrowed(term),
-            start_offset: start_offset,
-            position: position,
+            start_offset,
+            end_offset: start_offset + term.chars().count(),
+            offset_unit: OffsetUnit::Chars,
+            start_byte: start_offset,
+            end_byte: start_offset + term.len(),
+            position,
+            kind: TokenKind::Word,
+            location: None,
         }
     }
 
     pub fn term(&self) -> &str {
         self.term.as_ref()
     }
+
+    /// Narrows the offsets of a token covering `matched` to those of `slice`,
+    /// if it is a slice of `matched`.
+    fn narrow(&mut self, matched: &str, slice: &str) {
+        let start = (slice.as_ptr() as usize).wrapping_sub(matched.as_ptr() as usize);
+        if start > matched.len() || start + slice.len() > matched.len() {
+            return;
+        }
+        let unit = self.offset_unit;
+        let skipped = unit.measure(&matched[..start]);
+        self.start_byte += start;
+        self.end_byte = self.start_byte + slice.len();
+        self.start_offset += skipped;
+        self.end_offset = self.start_offset + unit.measure(slice);
+        if let Some(ref mut location) = self.location {
+            location.column += skipped;
+        }
+    }
 }
 
 impl<'a> fmt::Debug for Token<'a> {
@@ -101,91 +391,577 @@ impl<'a> fmt::Debug for Token<'a> {
     }
 }
 
+/// A snapshot of the progress of a FilteredTokenizer, from which tokenization
+/// can be resumed with `FilteredTokenizer::with_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenizerState {
+    /// The offset of the next char to be tokenized in bytes.
+    pub byte_offset: usize,
+
+    /// The offset of the next char to be tokenized in the offset unit of the
+    /// tokenizer, which is chars by default.
+    pub char_offset: usize,
+
+    /// The position that will be given to the next token.
+    pub position: usize,
+}
+
 /// Implementation of Tokenizer that extracts based on a provided Filter
 /// implementation.
 pub struct FilteredTokenizer<'a, T: filters::Filter> {
     filter: T,
     input: &'a str,
+    graphemes: bool,
+    offset_unit: Option<OffsetUnit>,
+    lines: Option<LineTracker>,
+    lossless: bool,
     byte_offset: usize,
-    char_offset: usize,
+    offset: usize,
     position: usize,
+    end: usize,
+    end_offset: Option<usize>,
+    end_position: usize,
+    overran: Option<usize>,
+    back: VecDeque<Token<'a>>,
 }
 
 impl<'a, T: filters::Filter> FilteredTokenizer<'a, T> {
     pub fn new(filter: T, input: &'a str) -> Self {
         FilteredTokenizer {
-            filter: filter,
-            input: input,
+            filter,
+            input,
+            graphemes: false,
+            offset_unit: None,
+            lines: None,
+            lossless: false,
             byte_offset: 0,
-            char_offset: 0,
+            offset: 0,
             position: 0,
+            end: input.len(),
+            end_offset: None,
+            end_position: 0,
+            overran: None,
+            back: VecDeque::new(),
+        }
+    }
+
+    /// Creates a tokenizer that resumes tokenizing `input` from a previously
+    /// saved state, such as one returned by `state`, without tokenizing the
+    /// input that precedes it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_offset` is beyond the end of the input or does not lie
+    /// on a char boundary.
+    pub fn with_state(
+        filter: T,
+        input: &'a str,
+        byte_offset: usize,
+        char_offset: usize,
+        position: usize,
+    ) -> Self {
+        assert!(
+            input.is_char_boundary(byte_offset),
+            "byte offset {} is not a char boundary of the input",
+            byte_offset
+        );
+        FilteredTokenizer {
+            byte_offset,
+            offset: char_offset,
+            position,
+            ..FilteredTokenizer::new(filter, input)
+        }
+    }
+
+    /// Moves the tokenizer back to the start of a token it previously
+    /// extracted from the same input.
+    pub(crate) fn resume(&mut self, token: &Token) {
+        self.byte_offset = token.start_byte;
+        self.offset = token.start_offset;
+        self.position = token.position;
+        if let (Some(lines), Some(location)) = (self.lines.as_mut(), token.location) {
+            lines.location = location;
+            lines.after_cr = self.input[..token.start_byte].ends_with('\r');
         }
     }
+
+    /// Starts tokenization from the byte offset `start`, counting offsets and
+    /// lines relative to it.
+    #[cfg(feature = "std")]
+    pub(crate) fn starting_from(mut self, start: usize) -> Self {
+        self.byte_offset = start;
+        self
+    }
+
+    /// Limits tokenization to the input preceding the byte offset `end`.
+    #[cfg(feature = "std")]
+    pub(crate) fn bounded(mut self, end: usize) -> Self {
+        self.end = end;
+        self
+    }
+
+    /// Tokenizes by extended grapheme cluster rather than by char, such that
+    /// combining marks and emoji sequences are never split from the char they
+    /// belong to. Each cluster is passed to `Filter::on_grapheme` and, unless
+    /// an offset unit is set, token offsets are counted in graphemes.
+    pub fn by_graphemes(mut self) -> Self {
+        self.graphemes = true;
+        self
+    }
+
+    /// Sets the unit in which token offsets are counted. Offsets are tracked
+    /// as the tokenizer advances and so are cheap to compute in any unit.
+    ///
+    /// Counting in graphemes also tokenizes by grapheme cluster, as a cluster
+    /// split between tokens could otherwise be counted once for each part.
+    pub fn offset_unit(mut self, unit: OffsetUnit) -> Self {
+        self.offset_unit = Some(unit);
+        if unit == OffsetUnit::Graphemes {
+            self.graphemes = true;
+        }
+        self
+    }
+
+    /// Tracks the line and column of each token as the tokenizer advances,
+    /// where columns are counted in the offset unit and tabs advance to the
+    /// next multiple of `tab_width`. Any of `\n`, `\r\n` and `\r` count as a
+    /// line break.
+    pub fn track_lines(mut self, tab_width: usize) -> Self {
+        self.lines = Some(LineTracker::new(tab_width));
+        self
+    }
+
+    /// Emits each run of chars discarded by the filter as a token of kind
+    /// `TokenKind::Trivia`, such that concatenating the terms of all tokens
+    /// reproduces the input exactly, provided the filter does not rewrite
+    /// terms with `Scanner::term`. Trivia tokens share the position of the
+    /// token that follows them.
+    pub fn lossless(mut self) -> Self {
+        self.lossless = true;
+        self
+    }
+
+    /// Returns the position that will be given to the next token.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns a snapshot of the progress of the tokenizer.
+    pub fn state(&self) -> TokenizerState {
+        TokenizerState {
+            byte_offset: self.byte_offset,
+            char_offset: self.offset,
+            position: self.position,
+        }
+    }
+
+    /// Moves the tokenizer to `byte_offset` without extracting any tokens,
+    /// and returns the offset it actually moved to. The offset is moved
+    /// forwards onto the next char, or grapheme, boundary, and if it lands
+    /// within a word, a compound token matched by the scanner of the filter,
+    /// or a run of trivia in lossless mode, then on past the end of it, such
+    /// that the next token is never a fragment. Offsets are updated
+    /// accordingly but positions carry on from the current position.
+    pub fn seek_to(&mut self, byte_offset: usize) -> usize {
+        let mut target = byte_offset.min(self.end);
+        while !self.input.is_char_boundary(target) {
+            target += 1;
+        }
+        if self.graphemes {
+            let mut cursor = GraphemeCursor::new(target, self.input.len(), true);
+            if !cursor.is_boundary(self.input, 0).unwrap_or(true) {
+                if let Ok(Some(boundary)) = cursor.next_boundary(self.input, 0) {
+                    target = boundary.min(self.end);
+                }
+            }
+        }
+
+        if self.filter.scanner().is_some() {
+            // A compound token may span delimiters, so the line is tokenized up
+            // to the target to find any token that covers it, along with any
+            // trivia carried over from the lines before it.
+            let mut start = line_start(self.input, target);
+            if self.lossless {
+                while let Some((len, (true, false))) = self.classify_before(start) {
+                    start -= len;
+                }
+            }
+            let tokens = FilteredTokenizer {
+                graphemes: self.graphemes,
+                lossless: self.lossless,
+                byte_offset: start,
+                end: self.end,
+                ..FilteredTokenizer::new(&self.filter, self.input)
+            };
+            for token in tokens {
+                if token.end_byte > target {
+                    if token.start_byte < target {
+                        target = token.end_byte;
+                    }
+                    break;
+                }
+            }
+        } else {
+            match self.classify_before(target) {
+                Some((_, (false, _))) => {
+                    while let Some((len, (false, _))) = self.classify(target) {
+                        target += len;
+                    }
+                }
+                // Discarded chars are kept as trivia tokens in lossless mode.
+                Some((_, (true, false))) if self.lossless => {
+                    while let Some((len, (true, false))) = self.classify(target) {
+                        target += len;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let unit = self.unit();
+        if target >= self.byte_offset {
+            self.skip(target - self.byte_offset);
+        } else {
+            self.offset -= unit.measure(&self.input[target..self.byte_offset]);
+            self.byte_offset = target;
+            if let Some(ref mut lines) = self.lines {
+                *lines = LineTracker::new(lines.tab_width);
+                lines.advance(&self.input[..target], unit);
+            }
+        }
+        target
+    }
+
+    /// Returns the length in bytes of the char or grapheme at `offset` along
+    /// with how it is classified by the filter.
+    #[inline]
+    fn classify(&self, offset: usize) -> Option<(usize, (bool, bool))> {
+        if !self.graphemes && offset < self.end {
+            let table = self.filter.ascii_table();
+            if let Some(class) = table.and_then(|t| t.class(self.input.as_bytes()[offset])) {
+                return Some((1, class));
+            }
+        }
+        self.classify_slow(offset)
+    }
+
+    /// Classifies the char or grapheme at `offset` without an ASCII table.
+    fn classify_slow(&self, offset: usize) -> Option<(usize, (bool, bool))> {
+        let rest = self.input.get(offset..self.end)?;
+        if self.graphemes {
+            let g = rest.graphemes(true).next()?;
+            return Some((g.len(), self.filter.on_grapheme(g)));
+        }
+        let c = rest.chars().next()?;
+        Some((c.len_utf8(), self.filter.on_char(&c)))
+    }
+
+    /// Returns the length in bytes and kind of a compound token matched by the
+    /// scanner of the filter at `offset`, if any. A match that runs past the
+    /// end of a bounded tokenizer is rejected and ends the tokenizer at its
+    /// start, recording where the match ends.
+    #[inline]
+    fn match_at(&mut self, offset: usize) -> Option<(usize, TokenKind)> {
+        match self.filter.scanner()?.scan(self.input, offset) {
+            Some((len, kind)) if len > 0 => {
+                if offset + len <= self.end {
+                    return Some((len, kind));
+                }
+                self.end = offset;
+                self.overran = Some(offset + len);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the length in bytes of the char or grapheme ending at `offset`
+    /// along with how it is classified by the filter.
+    fn classify_before(&self, offset: usize) -> Option<(usize, (bool, bool))> {
+        let rest = &self.input[..offset];
+        if self.graphemes {
+            let g = rest.graphemes(true).next_back()?;
+            Some((g.len(), self.filter.on_grapheme(g)))
+        } else {
+            let table = self.filter.ascii_table();
+            if let Some(class) = table.and_then(|t| t.class(*rest.as_bytes().last()?)) {
+                return Some((1, class));
+            }
+            let c = rest.chars().next_back()?;
+            Some((c.len_utf8(), self.filter.on_char(&c)))
+        }
+    }
+
+    /// Tokenizes the last run of input that is free of discarded chars, other
+    /// than trailing ones, into the back buffer.
+    fn tokenize_back(&mut self) {
+        let unit = self.unit();
+        let end_offset = match self.end_offset {
+            Some(offset) => offset,
+            None => self.offset + unit.measure(&self.input[self.byte_offset..self.end]),
+        };
+
+        // A compound token matched by the filter may begin anywhere, so the
+        // remaining input is tokenized as a whole for filters with a scanner.
+        let mut start = if self.filter.scanner().is_some() { self.byte_offset } else { self.end };
+        while start > self.byte_offset {
+            match self.classify_before(start) {
+                Some((len, (true, false))) => start -= len,
+                _ => break,
+            }
+        }
+        while start > self.byte_offset {
+            match self.classify_before(start) {
+                Some((_, (true, false))) | None => break,
+                Some((len, _)) => start -= len,
+            }
+        }
+        let start_offset = end_offset - unit.measure(&self.input[start..self.end]);
+
+        let tokens: Vec<Token<'a>> = FilteredTokenizer {
+            graphemes: self.graphemes,
+            offset_unit: Some(unit),
+            lossless: self.lossless,
+            byte_offset: start,
+            offset: start_offset,
+            end: self.end,
+            ..FilteredTokenizer::new(&self.filter, self.input)
+        }.collect();
+
+        self.back.extend(tokens);
+        self.end = start;
+        self.end_offset = Some(start_offset);
+    }
+
+    /// Returns the length in bytes of a match of `len` bytes from the current
+    /// offset, extended to the end of any grapheme it splits.
+    fn align(&self, len: usize) -> usize {
+        if !self.graphemes {
+            return len;
+        }
+        let mut end = 0;
+        for g in self.input[self.byte_offset..self.end].graphemes(true) {
+            if end >= len {
+                break;
+            }
+            end += g.len();
+        }
+        end
+    }
+
+    /// Returns the unit in which offsets are counted.
+    fn unit(&self) -> OffsetUnit {
+        match self.offset_unit {
+            Some(unit) => unit,
+            None if self.graphemes => OffsetUnit::Graphemes,
+            None => OffsetUnit::Chars,
+        }
+    }
+
+    /// Returns the length in the offset unit of the char or grapheme of `len`
+    /// bytes at `offset`.
+    #[inline]
+    fn units(&self, offset: usize, len: usize) -> usize {
+        // A single byte is an ASCII char, which is one unit in every unit.
+        if len == 1 {
+            return 1;
+        }
+        match self.unit() {
+            OffsetUnit::Bytes => len,
+            OffsetUnit::Graphemes => 1,
+            unit if self.graphemes => unit.measure(&self.input[offset..offset + len]),
+            OffsetUnit::Utf16 if len == 4 => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns the length in bytes of the word starting at `offset` along with
+    /// its length in the offset unit, counting chars as they are classified
+    /// rather than measuring the word afterwards when tokenizing by char.
+    fn word_len(&self, offset: usize) -> (usize, usize) {
+        let mut end = offset;
+        if self.graphemes {
+            while let Some((len, (false, _))) = self.classify_slow(end) {
+                end += len;
+            }
+            return (end - offset, self.unit().measure(&self.input[offset..end]));
+        }
+
+        // Runs of ASCII are skipped over a block at a time when the filter has
+        // a table for them.
+        let table = self.filter.ascii_table();
+        let bytes = &self.input.as_bytes()[..self.end];
+        let mut units = 0;
+        loop {
+            if let Some(table) = table {
+                let len = ascii::word_len(table, &bytes[end..]);
+                end += len;
+                units += len;
+
+                // The table has already classified any ASCII byte it stopped
+                // at as a delimiter.
+                if bytes.get(end).map_or(true, u8::is_ascii) {
+                    break;
+                }
+            }
+            match self.classify(end) {
+                Some((len, (false, _))) => {
+                    units += self.units(end, len);
+                    end += len;
+                }
+                _ => break,
+            }
+        }
+        (end - offset, units)
+    }
+
+    /// Advances past the next `byte_len` bytes of input, which span `units`
+    /// in the offset unit, without extracting them.
+    #[inline]
+    fn advance(&mut self, byte_len: usize, units: usize) {
+        let unit = self.unit();
+        if let Some(ref mut lines) = self.lines {
+            lines.advance(&self.input[self.byte_offset..self.byte_offset + byte_len], unit);
+        }
+        self.offset += units;
+        self.byte_offset += byte_len;
+    }
+
+    /// Advances past the next `byte_len` bytes of input without extracting
+    /// them.
+    fn skip(&mut self, byte_len: usize) {
+        let slice = &self.input[self.byte_offset..self.byte_offset + byte_len];
+        let units = self.unit().measure(slice);
+        self.advance(byte_len, units);
+    }
+
+    /// Extracts the next `byte_len` bytes of input as a token and advances
+    /// past it.
+    fn take(&mut self, byte_len: usize, kind: TokenKind) -> Token<'a> {
+        let slice = &self.input[self.byte_offset..self.byte_offset + byte_len];
+        let units = self.unit().measure(slice);
+        self.take_units(byte_len, units, kind)
+    }
+
+    /// Extracts the next `byte_len` bytes of input, which span `units` in the
+    /// offset unit, as a token and advances past it.
+    #[inline]
+    fn take_units(&mut self, byte_len: usize, units: usize, kind: TokenKind) -> Token<'a> {
+        let start_byte = self.byte_offset;
+        let start_offset = self.offset;
+        let location = self.lines.as_ref().map(|l| l.location);
+        self.advance(byte_len, units);
+        let token = Token {
+            term: Cow::Borrowed(&self.input[start_byte..self.byte_offset]),
+            start_offset,
+            end_offset: self.offset,
+            offset_unit: self.unit(),
+            start_byte,
+            end_byte: self.byte_offset,
+            position: self.position,
+            kind,
+            location,
+        };
+        if kind != TokenKind::Trivia {
+            self.position += 1;
+        }
+        token
+    }
 }
 
 impl<'a, T: filters::Filter> Iterator for FilteredTokenizer<'a, T> {
     type Item = Token<'a>;
 
     fn next(&mut self) -> Option<Token<'a>> {
-        let mut skipped_bytes = 0;
-        let mut skipped_chars = 0;
-
-        let filter = &self.filter;
-
-        // cidx, bidx is the char and byte index from the last found separator
-        for (cidx, bidx, c, is_keep) in
-            self.input[self.byte_offset..]
-                .char_indices()
-                .enumerate()
-                // Remove any drop codes entirely
-                .filter_map(|(ci, (bi, c))| {
-                    let (is_filtered, is_keep) = filter.on_char(&c);
-                    if is_filtered {
-                        Some((ci, bi, c, is_keep))
-                    } else {
-                        None
+        // Walk over delimiters until we reach the start of a token, emitting
+        // keep chars and any compound tokens matched by the filter on the way.
+        loop {
+            let (len, (is_filtered, is_keep)) = match self.classify(self.byte_offset) {
+                Some(class) => class,
+                None => {
+                    // Any remaining tokens were buffered by next_back.
+                    let mut token = self.back.pop_front()?;
+                    token.position = self.position;
+                    if token.kind != TokenKind::Trivia {
+                        self.position += 1;
                     }
-                })
-        {
-            let char_len = c.len_utf8();
-
-            // If we found a separator but had no text beforehand simply move
-            // our counters to the new position.
-            if cidx == skipped_chars {
-                self.char_offset += 1;
-                self.byte_offset += char_len;
-                skipped_bytes += char_len;
-                skipped_chars += 1;
-                if is_keep {
-                    let slice = &self.input[self.byte_offset - char_len..
-                                                self.byte_offset + bidx + char_len - skipped_bytes];
-                    let token = Token::from_str(slice, self.char_offset - 1, self.position);
-                    self.position += 1;
                     return Some(token);
                 }
+            };
+
+            if let Some((len, kind)) = self.match_at(self.byte_offset) {
+                let len = self.align(len);
+                let matched = &self.input[self.byte_offset..self.byte_offset + len];
+                let scanner = self.filter.scanner();
+                let term = scanner.map_or(Cow::Borrowed(matched), |s| s.term(matched, kind));
+                let mut token = self.take(len, kind);
+                if let Cow::Borrowed(slice) = term {
+                    token.narrow(matched, slice);
+                }
+                token.term = term;
+                return Some(token);
+            }
+            // A match that overran a bounded tokenizer ends it here.
+            if self.byte_offset == self.end {
                 continue;
             }
 
-            let slice = &self.input[self.byte_offset..self.byte_offset + bidx - skipped_bytes];
-            let token = Token::from_str(slice, self.char_offset, self.position);
+            if !is_filtered {
+                break;
+            }
+            let units = self.units(self.byte_offset, len);
+            if is_keep {
+                return Some(self.take_units(len, units, TokenKind::Punctuation));
+            }
+            if !self.lossless {
+                self.advance(len, units);
+                continue;
+            }
 
-            self.char_offset += slice.chars().count();
-            self.position += 1;
-            self.byte_offset += bidx - skipped_bytes;
-            if !is_keep {
-                self.char_offset += 1;
-                self.byte_offset += char_len;
+            let mut end = self.byte_offset + len;
+            while end < self.end && self.match_at(end).is_none() {
+                match self.classify(end) {
+                    Some((len, (true, false))) => end += len,
+                    _ => break,
+                }
             }
-            return Some(token);
+            let byte_len = end - self.byte_offset;
+            return Some(self.take(byte_len, TokenKind::Trivia));
         }
 
-        if self.byte_offset < self.input.len() {
-            let slice = &self.input[self.byte_offset..];
-            let token = Token::from_str(slice, self.char_offset, self.position);
-            self.byte_offset = self.input.len();
-            Some(token)
-        } else {
-            None
+        // Consume chars up until the next delimiter or the end of the input.
+        let (byte_len, units) = self.word_len(self.byte_offset);
+        Some(self.take_units(byte_len, units, TokenKind::Word))
+    }
+}
+
+/// Tokens are taken from the back by tokenizing backwards one run of input
+/// free of discarded chars at a time, which gives the same tokens as iterating
+/// forwards. For filters that match compound tokens, which may contain
+/// discarded chars, the remaining input is instead tokenized forwards into a
+/// buffer on the first call to `next_back`.
+///
+/// Since the number of tokens before the back of the input is unknown, tokens
+/// taken from the back are numbered by their distance from the end of the
+/// input, the last token having position 0, and lines are not tracked.
+/// Trivia taken from the back share the position of the token that follows
+/// them.
+impl<'a, T: filters::Filter> DoubleEndedIterator for FilteredTokenizer<'a, T> {
+    fn next_back(&mut self) -> Option<Token<'a>> {
+        loop {
+            if let Some(mut token) = self.back.pop_back() {
+                if token.kind == TokenKind::Trivia {
+                    token.position = self.end_position.saturating_sub(1);
+                } else {
+                    token.position = self.end_position;
+                    self.end_position += 1;
+                }
+                return Some(token);
+            }
+            if self.end <= self.byte_offset {
+                return None;
+            }
+            self.tokenize_back();
         }
     }
 }
@@ -0,0 +1,454 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Tokenizes files or stdin from the command line. Run with `--help` for
+//! usage.
+
+extern crate tokesies;
+
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::process;
+
+use tokesies::filters::{self, Filter};
+use tokesies::token_filters::{self, TokenFilter, TokenFilterExt};
+use tokesies::{FilteredTokenizer, Token};
+
+const USAGE: &str = "\
+Usage: tokesies [OPTIONS] [FILE]...
+
+Tokenizes each FILE, or stdin when no FILE is given or FILE is -, and writes
+the tokens to stdout. Offsets and positions restart for each FILE.
+
+Options:
+  -f, --filter <NAME>        default, whitespace or custom [default: default]
+      --keep <CHARS>         chars the custom filter collects as tokens
+      --drop <CHARS>         chars the custom filter discards
+  -t, --token-filter <NAME>  url_components or identifier_split, applied in
+                             the order given
+  -o, --output <FORMAT>      lines, tsv or jsonl [default: lines]
+  -h, --help                 print this help
+
+CHARS may contain the escapes \\t, \\n, \\r, \\s (space) and \\\\.
+
+The tsv format has the columns term, start, end, position and kind, with
+offsets in chars and tabs, line breaks and backslashes in terms escaped.
+
+Exit status is 0 on success, 1 if a FILE cannot be read, 2 for invalid
+options and 3 if an input is not valid UTF-8.";
+
+const EXIT_IO: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_INVALID_UTF8: i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Output {
+    Lines,
+    Tsv,
+    JsonLines,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    filter: String,
+    keep: String,
+    drop: String,
+    token_filters: Vec<String>,
+    output: Output,
+    files: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Run(Options),
+}
+
+/// An error that ends the program with an exit status and a message.
+#[derive(Debug, PartialEq)]
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+fn usage_error(message: String) -> Failure {
+    Failure {
+        code: EXIT_USAGE,
+        message,
+    }
+}
+
+fn unescape(chars: &str) -> String {
+    let mut unescaped = String::with_capacity(chars.len());
+    let mut iter = chars.chars();
+    while let Some(c) = iter.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match iter.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('s') => unescaped.push(' '),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Command, Failure> {
+    let mut options = Options {
+        filter: "default".to_string(),
+        keep: String::new(),
+        drop: String::new(),
+        token_filters: Vec::new(),
+        output: Output::Lines,
+        files: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| usage_error(format!("{} requires a value", name)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-f" | "--filter" => options.filter = value(&arg)?,
+            "--keep" => options.keep = unescape(&value(&arg)?),
+            "--drop" => options.drop = unescape(&value(&arg)?),
+            "-t" | "--token-filter" => options.token_filters.push(value(&arg)?),
+            "-o" | "--output" => {
+                options.output = match value(&arg)?.as_str() {
+                    "lines" => Output::Lines,
+                    "tsv" => Output::Tsv,
+                    "jsonl" => Output::JsonLines,
+                    other => return Err(usage_error(format!("unknown output format: {}", other))),
+                }
+            }
+            "-" => options.files.push(arg),
+            _ if arg.starts_with('-') => {
+                return Err(usage_error(format!("unknown option: {}", arg)));
+            }
+            _ => options.files.push(arg),
+        }
+    }
+    if options.files.is_empty() {
+        options.files.push("-".to_string());
+    }
+    Ok(Command::Run(options))
+}
+
+fn build_filter(options: &Options) -> Result<Box<dyn Filter>, Failure> {
+    match options.filter.as_str() {
+        "default" => Ok(Box::new(filters::DefaultFilter)),
+        "whitespace" => Ok(Box::new(filters::WhitespaceFilter)),
+        "custom" => Ok(Box::new(filters::TableFilter::from_chars(&options.keep, &options.drop))),
+        other => Err(usage_error(format!("unknown filter: {}", other))),
+    }
+}
+
+fn build_token_filter(name: &str) -> Result<Box<dyn TokenFilter>, Failure> {
+    match name {
+        "url_components" => Ok(Box::new(token_filters::UrlComponentFilter)),
+        "identifier_split" => Ok(Box::new(token_filters::IdentifierSplitFilter::default())),
+        other => Err(usage_error(format!("unknown token filter: {}", other))),
+    }
+}
+
+/// Returns the kind of a token in snake case, such as `ip_address`.
+fn kind_name(token: &Token) -> String {
+    let mut name = String::new();
+    for (i, c) in format!("{:?}", token.kind).chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.extend(c.to_lowercase());
+    }
+    name
+}
+
+fn write_escaped_tsv(out: &mut String, term: &str) {
+    for c in term.chars() {
+        match c {
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\\' => out.push_str("\\\\"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn write_escaped_json(out: &mut String, term: &str) {
+    out.push('"');
+    for c in term.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn format_token(output: Output, token: &Token, line: &mut String) {
+    line.clear();
+    match output {
+        Output::Lines => line.push_str(token.term()),
+        Output::Tsv => {
+            write_escaped_tsv(line, token.term());
+            let _ = write!(
+                line,
+                "\t{}\t{}\t{}\t{}",
+                token.start_offset,
+                token.end_offset,
+                token.position,
+                kind_name(token)
+            );
+        }
+        Output::JsonLines => {
+            line.push_str("{\"term\":");
+            write_escaped_json(line, token.term());
+            let _ = write!(
+                line,
+                ",\"start\":{},\"end\":{},\"position\":{},\"kind\":\"{}\"}}",
+                token.start_offset,
+                token.end_offset,
+                token.position,
+                kind_name(token)
+            );
+        }
+    }
+    line.push('\n');
+}
+
+fn read_input(file: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    if file == "-" {
+        io::stdin().read_to_end(&mut bytes)?;
+    } else {
+        fs::File::open(file)?.read_to_end(&mut bytes)?;
+    }
+    Ok(bytes)
+}
+
+fn tokenize<W: Write>(
+    options: &Options,
+    filter: &dyn Filter,
+    token_filters: &[Box<dyn TokenFilter>],
+    input: &str,
+    out: &mut W,
+) -> io::Result<()> {
+    let mut tokens: Box<dyn Iterator<Item = Token>> =
+        Box::new(FilteredTokenizer::new(filter, input));
+    for token_filter in token_filters {
+        tokens = Box::new(tokens.filter_tokens(&**token_filter));
+    }
+
+    let mut line = String::new();
+    for token in tokens {
+        format_token(options.output, &token, &mut line);
+        out.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Tokenizes each file of the options to `out`, reading them with `read`, and
+/// returns the exit status.
+fn run<R, W>(options: &Options, mut read: R, out: &mut W) -> Result<i32, Failure>
+where
+    R: FnMut(&str) -> io::Result<Vec<u8>>,
+    W: Write,
+{
+    let filter = build_filter(options)?;
+    let token_filters = options
+        .token_filters
+        .iter()
+        .map(|name| build_token_filter(name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut status = 0;
+    for file in &options.files {
+        let name = if file == "-" { "stdin" } else { file.as_str() };
+        let bytes = match read(file) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                eprintln!("tokesies: {}: {}", name, err);
+                status = EXIT_IO;
+                continue;
+            }
+        };
+        let input = match std::str::from_utf8(&bytes) {
+            Ok(input) => input,
+            Err(err) => {
+                let _ = out.flush();
+                return Err(Failure {
+                    code: EXIT_INVALID_UTF8,
+                    message: format!("{}: invalid UTF-8 at byte {}", name, err.valid_up_to()),
+                });
+            }
+        };
+
+        let result = tokenize(options, &*filter, &token_filters, input, out);
+        if let Err(err) = result.and_then(|_| out.flush()) {
+            if err.kind() == io::ErrorKind::BrokenPipe {
+                return Ok(status);
+            }
+            return Err(Failure {
+                code: EXIT_IO,
+                message: err.to_string(),
+            });
+        }
+    }
+    Ok(status)
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(|command| match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        Command::Run(options) => {
+            let stdout = io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            run(&options, read_input, &mut out)
+        }
+    });
+    match result {
+        Ok(status) => process::exit(status),
+        Err(failure) => {
+            eprintln!("tokesies: {}", failure.message);
+            process::exit(failure.code);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Command, Failure> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    fn options(list: &[&str]) -> Options {
+        match args(list) {
+            Ok(Command::Run(options)) => options,
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    /// Runs the options over inputs read from a map of file names to bytes,
+    /// returning the result and the output.
+    fn run_with(options: &Options, files: &[(&str, &[u8])]) -> (Result<i32, Failure>, String) {
+        let mut out = Vec::new();
+        let read = |file: &str| match files.iter().find(|f| f.0 == file) {
+            Some(f) => Ok(f.1.to_vec()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "not found")),
+        };
+        let result = run(options, read, &mut out);
+        (result, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn parse_args_case() {
+        assert_eq!(Ok(Command::Help), args(&["-o", "tsv", "--help"]));
+
+        let parsed = options(&["-f", "custom", "--keep", ",", "--drop", "\\s\\t", "-o", "jsonl"]);
+        assert_eq!("custom", parsed.filter);
+        assert_eq!(",", parsed.keep);
+        assert_eq!(" \t", parsed.drop);
+        assert_eq!(Output::JsonLines, parsed.output);
+        assert_eq!(vec!["-".to_string()], parsed.files);
+
+        let parsed = options(&["-t", "url_components", "a.txt", "-"]);
+        assert_eq!(vec!["url_components".to_string()], parsed.token_filters);
+        assert_eq!(vec!["a.txt".to_string(), "-".to_string()], parsed.files);
+
+        for invalid in &[&["-f"][..], &["--nope"], &["-o", "xml"]] {
+            assert_eq!(Some(EXIT_USAGE), args(invalid).err().map(|f| f.code));
+        }
+    }
+
+    #[test]
+    fn output_formats_case() {
+        let input: &[u8] = b"hi \"there\"\ta\\b";
+
+        let (result, out) = run_with(&options(&[]), &[("-", input)]);
+        assert_eq!(Ok(0), result);
+        assert_eq!("hi\n\"\nthere\n\"\na\n\\\nb\n", out);
+
+        let (result, out) = run_with(&options(&["-f", "whitespace", "-o", "tsv"]), &[("-", input)]);
+        assert_eq!(Ok(0), result);
+        assert_eq!("hi\t0\t2\t0\tword\n\"there\"\t3\t10\t1\tword\na\\\\b\t11\t14\t2\tword\n", out);
+
+        let (result, out) = run_with(&options(&["-o", "jsonl", "-"]), &[("-", b"say \"hi\"")]);
+        assert_eq!(Ok(0), result);
+        assert_eq!(
+            "{\"term\":\"say\",\"start\":0,\"end\":3,\"position\":0,\"kind\":\"word\"}\n\
+             {\"term\":\"\\\"\",\"start\":4,\"end\":5,\"position\":1,\"kind\":\"punctuation\"}\n\
+             {\"term\":\"hi\",\"start\":5,\"end\":7,\"position\":2,\"kind\":\"word\"}\n\
+             {\"term\":\"\\\"\",\"start\":7,\"end\":8,\"position\":3,\"kind\":\"punctuation\"}\n",
+            out
+        );
+
+        let custom = options(&["-f", "custom", "--keep", ",", "--drop", "\\s", "-o", "tsv"]);
+        let (result, out) = run_with(&custom, &[("-", b"a,b c\td")]);
+        assert_eq!(Ok(0), result);
+        assert_eq!(
+            "a\t0\t1\t0\tword\n,\t1\t2\t1\tpunctuation\nb\t2\t3\t2\tword\nc\\td\t4\t7\t3\tword\n",
+            out
+        );
+    }
+
+    #[test]
+    fn exit_status_case() {
+        let (result, out) =
+            run_with(&options(&["a.txt", "b.txt"]), &[("a.txt", b"ok"), ("b.txt", b"x \xff y")]);
+        assert_eq!(
+            Err(Failure {
+                code: EXIT_INVALID_UTF8,
+                message: "b.txt: invalid UTF-8 at byte 2".to_string(),
+            }),
+            result
+        );
+        assert_eq!("ok\n", out);
+
+        let (result, out) = run_with(&options(&["missing.txt", "-"]), &[("-", b"ok")]);
+        assert_eq!(Ok(EXIT_IO), result);
+        assert_eq!("ok\n", out);
+
+        let (result, _) = run_with(&options(&["-f", "nope"]), &[("-", b"ok")]);
+        assert_eq!(Some(EXIT_USAGE), result.err().map(|f| f.code));
+        let (result, _) = run_with(&options(&["-t", "nope"]), &[("-", b"ok")]);
+        assert_eq!(Some(EXIT_USAGE), result.err().map(|f| f.code));
+    }
+}
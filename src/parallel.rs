@@ -0,0 +1,248 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use std::thread;
+
+use unicode_segmentation::GraphemeCursor;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use filters::Filter;
+use {FilteredTokenizer, LineColumn, Token};
+
+/// The tokens of one chunk of input, with offsets, positions and lines
+/// relative to the start of the chunk.
+struct Chunk<'a> {
+    tokens: Vec<Token<'a>>,
+    len: usize,
+    positions: usize,
+    end_location: Option<LineColumn>,
+    // The byte offset tokenization stopped at, which is the start of any
+    // match that overran the chunk, and the byte offset that match ends at.
+    stop: usize,
+    overran: Option<usize>,
+}
+
+/// Tokenizes large inputs by splitting them into chunks that are tokenized on
+/// multiple threads, producing exactly the same tokens, offsets and positions
+/// as tokenizing the whole input with a single FilteredTokenizer.
+///
+/// Chunks are split following a char discarded by the filter, or following a
+/// line break when lines are tracked. If the filter matches a compound token
+/// that runs over the end of a chunk, such as a quoted string containing
+/// spaces, tokenization of the chunk continues from the start of the match up
+/// to the end of the chunk in which the match ends.
+///
+/// Uses the rayon thread pool when the `rayon` feature is enabled, and scoped
+/// std threads otherwise.
+pub struct ParallelTokenizer<F> {
+    tokenizer: F,
+    chunk_size: usize,
+    threads: usize,
+}
+
+impl<F, T> ParallelTokenizer<F>
+where
+    F: for<'a> Fn(&'a str) -> FilteredTokenizer<'a, T> + Sync,
+    T: Filter,
+{
+    /// Creates a parallel tokenizer from a function that creates a configured
+    /// FilteredTokenizer for an input.
+    pub fn new(tokenizer: F) -> Self {
+        ParallelTokenizer {
+            tokenizer,
+            chunk_size: 1 << 20,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Sets the approximate size of each chunk in bytes.
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Sets the number of threads to use when the `rayon` feature is disabled.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Returns the byte offsets at which the input is split into chunks,
+    /// including the start and end of the input.
+    fn boundaries(&self, input: &str) -> Vec<usize> {
+        let tokenizer = (self.tokenizer)(input);
+        let by_line = tokenizer.lines.is_some();
+        let mut bounds = vec![0];
+        let mut target = self.chunk_size;
+
+        while target < input.len() {
+            while !input.is_char_boundary(target) {
+                target += 1;
+            }
+            if tokenizer.graphemes {
+                let mut cursor = GraphemeCursor::new(target, input.len(), true);
+                if !cursor.is_boundary(input, 0).unwrap_or(true) {
+                    if let Ok(Some(boundary)) = cursor.next_boundary(input, 0) {
+                        target = boundary;
+                    }
+                }
+            }
+            while target < input.len() {
+                let after_drop = match tokenizer.classify_before(target) {
+                    Some((len, (true, false))) => {
+                        !by_line || input[target - len..target].ends_with('\n')
+                    }
+                    _ => false,
+                };
+                let (len, class) = match tokenizer.classify(target) {
+                    Some(c) => c,
+                    None => break,
+                };
+                if after_drop && class != (true, false) {
+                    break;
+                }
+                target += len;
+            }
+            if target < input.len() {
+                bounds.push(target);
+            }
+            target += self.chunk_size;
+        }
+        bounds.push(input.len());
+        bounds
+    }
+
+    fn tokenize_chunk<'a>(&self, input: &'a str, start: usize, end: usize) -> Chunk<'a> {
+        let mut chunk = Chunk {
+            tokens: Vec::new(),
+            len: 0,
+            positions: 0,
+            end_location: None,
+            stop: start,
+            overran: None,
+        };
+        self.extend_chunk(input, &mut chunk, end);
+        chunk
+    }
+
+    /// Continues tokenizing a chunk from where it stopped up to `end`, such
+    /// that tokens preceding a match that overran the chunk are kept.
+    fn extend_chunk<'a>(&self, input: &'a str, chunk: &mut Chunk<'a>, end: usize) {
+        let mut tokenizer = (self.tokenizer)(input).starting_from(chunk.stop).bounded(end);
+        tokenizer.offset = chunk.len;
+        tokenizer.position = chunk.positions;
+        if let (Some(lines), Some(location)) = (tokenizer.lines.as_mut(), chunk.end_location) {
+            lines.location = location;
+            lines.after_cr = input[..chunk.stop].ends_with('\r');
+        }
+        chunk.tokens.extend(tokenizer.by_ref());
+        chunk.len = tokenizer.offset;
+        chunk.positions = tokenizer.position;
+        chunk.end_location = tokenizer.lines.as_ref().map(|l| l.location);
+        chunk.stop = tokenizer.byte_offset;
+        chunk.overran = tokenizer.overran;
+    }
+
+    #[cfg(feature = "rayon")]
+    fn tokenize_chunks<'a>(&self, input: &'a str, bounds: &[usize]) -> Vec<Chunk<'a>> {
+        bounds
+            .par_windows(2)
+            .map(|w| self.tokenize_chunk(input, w[0], w[1]))
+            .collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn tokenize_chunks<'a>(&self, input: &'a str, bounds: &[usize]) -> Vec<Chunk<'a>> {
+        let windows: Vec<&[usize]> = bounds.windows(2).collect();
+        let per_thread = windows.len().div_ceil(self.threads).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = windows
+                .chunks(per_thread)
+                .map(|group| {
+                    scope.spawn(move || {
+                        group
+                            .iter()
+                            .map(|w| self.tokenize_chunk(input, w[0], w[1]))
+                            .collect::<Vec<Chunk<'a>>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().expect("tokenizer thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Tokenizes an input.
+    pub fn tokenize<'a>(&self, input: &'a str) -> Vec<Token<'a>> {
+        let bounds = self.boundaries(input);
+        let mut chunks: Vec<Option<Chunk>> = self
+            .tokenize_chunks(input, &bounds)
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        let total = chunks.iter().flatten().map(|c| c.tokens.len()).sum();
+        let mut tokens = Vec::with_capacity(total);
+        let mut offset = 0;
+        let mut position = 0;
+        let mut location = LineColumn { line: 1, column: 1 };
+        let mut i = 0;
+        while i < chunks.len() {
+            let mut chunk = chunks[i].take().expect("chunk already stitched");
+            let mut next = i + 1;
+            while let Some(match_end) = chunk.overran {
+                next = bounds.partition_point(|&b| b < match_end);
+                self.extend_chunk(input, &mut chunk, bounds[next]);
+            }
+
+            for mut token in chunk.tokens {
+                token.start_offset += offset;
+                token.end_offset += offset;
+                token.position += position;
+                if let Some(ref mut l) = token.location {
+                    if l.line == 1 {
+                        l.column += location.column - 1;
+                    }
+                    l.line += location.line - 1;
+                }
+                tokens.push(token);
+            }
+
+            offset += chunk.len;
+            position += chunk.positions;
+            if let Some(end) = chunk.end_location {
+                if end.line == 1 {
+                    location.column += end.column - 1;
+                } else {
+                    location = LineColumn {
+                        line: location.line + end.line - 1,
+                        column: end.column,
+                    };
+                }
+            }
+            i = next;
+        }
+        tokens
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use std::collections::HashSet;
+
+use unicode_segmentation::{UnicodeSegmentation, USentenceBoundIndices};
+
+use filters::Filter;
+use FilteredTokenizer;
+
+/// Abbreviations that are followed by a full stop without ending a sentence.
+static DEFAULT_ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "mt", "rev", "gen", "col", "lt", "sgt", "capt",
+    "gov", "sen", "rep", "vs", "etc", "e.g", "i.e", "cf", "approx", "inc", "ltd", "corp", "dept",
+    "fig", "vol", "pp", "jan", "feb", "apr", "jun", "jul", "aug", "sep", "sept", "oct", "nov",
+    "dec",
+];
+
+/// Abbreviations that are also ordinary words, and so only prevent a full
+/// stop from ending a sentence when the next word starts with a lowercase
+/// letter or a digit, as in `No. 5` but not `I said no. Then`.
+static AMBIGUOUS_ABBREVIATIONS: &[&str] = &["no", "mar", "co", "est", "al", "st"];
+
+/// A sentence extracted from an input.
+pub struct Sentence<'a> {
+    /// The content of the sentence, without trailing whitespace.
+    pub text: &'a str,
+
+    /// The absolute offset of the sentence in bytes.
+    pub byte_offset: usize,
+
+    /// The absolute offset of the sentence in chars.
+    pub char_offset: usize,
+
+    input: &'a str,
+}
+
+impl<'a> Sentence<'a> {
+    /// Tokenizes the sentence with the provided filter. Token offsets are in
+    /// chars, absolute to the input the sentence was extracted from, and
+    /// positions begin at `position`, which allows positions to continue
+    /// across sentences by passing the `position` of the previous tokenizer.
+    pub fn tokenize<T: Filter>(&self, filter: T, position: usize) -> FilteredTokenizer<'a, T> {
+        let (start, end) = (self.byte_offset, self.byte_offset + self.text.len());
+        FilteredTokenizer::with_state(filter, self.input, start, self.char_offset, position)
+            .bounded(end)
+    }
+}
+
+/// Splits an input into sentences following the Unicode sentence boundary
+/// rules of UAX #29, with the exception that a full stop following a known
+/// abbreviation, as in `Dr. Dobb`, does not end a sentence.
+pub struct SentenceTokenizer<'a> {
+    input: &'a str,
+    bounds: USentenceBoundIndices<'a>,
+    abbreviations: HashSet<String>,
+    ambiguous: HashSet<String>,
+    char_offset: usize,
+}
+
+impl<'a> SentenceTokenizer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let abbreviations = DEFAULT_ABBREVIATIONS.iter().map(|a| a.to_string()).collect();
+        SentenceTokenizer {
+            ambiguous: AMBIGUOUS_ABBREVIATIONS.iter().map(|a| a.to_string()).collect(),
+            ..SentenceTokenizer::with_abbreviations(input, abbreviations)
+        }
+    }
+
+    /// Creates a sentence tokenizer with a custom set of abbreviations, which
+    /// are matched case insensitively and without their trailing full stop.
+    pub fn with_abbreviations(input: &'a str, abbreviations: HashSet<String>) -> Self {
+        SentenceTokenizer {
+            input,
+            bounds: input.split_sentence_bound_indices(),
+            abbreviations: abbreviations.into_iter().map(|a| a.to_lowercase()).collect(),
+            ambiguous: HashSet::new(),
+            char_offset: 0,
+        }
+    }
+
+    /// Returns whether a sentence ending with `text` continues into `rest`
+    /// because its final full stop follows an abbreviation.
+    fn continues(&self, text: &str, rest: &str) -> bool {
+        let text = text.trim_end();
+        if !text.ends_with('.') {
+            return false;
+        }
+        let text = &text[..text.len() - 1];
+        let word = match text.rfind(|c: char| c.is_whitespace() || c == '(' || c == '"') {
+            Some(i) => &text[i + 1..],
+            None => text,
+        };
+        if word.is_empty() {
+            return false;
+        }
+        let word = word.to_lowercase();
+        if self.abbreviations.contains(&word) {
+            return true;
+        }
+        let next = rest.trim_start().chars().next();
+        self.ambiguous.contains(&word) && next.is_some_and(|c| c.is_lowercase() || c.is_numeric())
+    }
+}
+
+impl<'a> Iterator for SentenceTokenizer<'a> {
+    type Item = Sentence<'a>;
+
+    fn next(&mut self) -> Option<Sentence<'a>> {
+        let (start, first) = self.bounds.next()?;
+        let mut end = start + first.len();
+        while self.continues(&self.input[start..end], &self.input[end..]) {
+            match self.bounds.next() {
+                Some((_, s)) => end += s.len(),
+                None => break,
+            }
+        }
+
+        let raw = &self.input[start..end];
+        let sentence = Sentence {
+            text: raw.trim_end(),
+            byte_offset: start,
+            char_offset: self.char_offset,
+            input: self.input,
+        };
+        self.char_offset += raw.chars().count();
+        Some(sentence)
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Types shared by the subword tokenizers, which split the tokens extracted by
+//! a FilteredTokenizer into pieces from a fixed vocabulary for use with
+//! machine learning models.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::CharIndices;
+
+use Token;
+
+/// A piece of a token along with its id in the vocabulary of a subword model.
+///
+/// The term of the piece is the text it covers in the input, and its offsets
+/// are in the same unit as those of the token it was taken from. Pieces are
+/// numbered by their position in the encoded sequence.
+#[derive(Debug)]
+pub struct SubToken<'a> {
+    pub token: Token<'a>,
+    pub id: u32,
+}
+
+/// An error loading the vocabulary of a subword model.
+#[derive(Debug)]
+pub enum VocabError {
+    /// The vocabulary could not be read.
+    Io(io::Error),
+
+    /// A line of the vocabulary, counting from 1, could not be parsed.
+    Parse { line: usize, message: String },
+
+    /// A token required by the model, such as the unknown token, is missing
+    /// from the vocabulary.
+    MissingToken(String),
+}
+
+impl fmt::Display for VocabError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VocabError::Io(ref err) => write!(f, "failed to read vocabulary: {}", err),
+            VocabError::Parse { line, ref message } => {
+                write!(f, "invalid vocabulary at line {}: {}", line, message)
+            }
+            VocabError::MissingToken(ref token) => {
+                write!(f, "vocabulary is missing the token {}", token)
+            }
+        }
+    }
+}
+
+impl Error for VocabError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            VocabError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VocabError {
+    fn from(err: io::Error) -> Self {
+        VocabError::Io(err)
+    }
+}
+
+/// Reads a vocabulary file.
+pub(crate) fn read_vocab<P: AsRef<Path>>(path: P) -> Result<String, VocabError> {
+    Ok(fs::read_to_string(path)?)
+}
+
+/// Parses a `vocab.json` file, which is a JSON object mapping pieces to ids.
+pub(crate) fn parse_json_vocab(json: &str) -> Result<HashMap<String, u32>, VocabError> {
+    let mut parser = JsonParser {
+        json,
+        chars: json.char_indices().peekable(),
+    };
+    let mut vocab = HashMap::new();
+
+    parser.expect('{')?;
+    if parser.peek() == Some('}') {
+        parser.chars.next();
+    } else {
+        loop {
+            let piece = parser.string()?;
+            parser.expect(':')?;
+            let id = parser.number()?;
+            vocab.insert(piece, id);
+            match parser.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err(parser.error("expected , or }")),
+            }
+        }
+    }
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected content after the object"));
+    }
+    Ok(vocab)
+}
+
+/// Just enough of a JSON parser to read a flat object of strings to numbers.
+struct JsonParser<'a> {
+    json: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&mut self, message: &str) -> VocabError {
+        let offset = self.chars.peek().map_or(self.json.len(), |c| c.0);
+        VocabError::Parse {
+            line: self.json[..offset].matches('\n').count() + 1,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                return Some(c);
+            }
+            self.chars.next();
+        }
+        None
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.peek()?;
+        self.chars.next().map(|c| c.1)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), VocabError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected {}", expected))),
+        }
+    }
+
+    fn hex(&mut self) -> Result<u32, VocabError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            match self.chars.next().and_then(|c| c.1.to_digit(16)) {
+                Some(digit) => value = value * 16 + digit,
+                None => return Err(self.error("invalid unicode escape")),
+            }
+        }
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String, VocabError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = match self.chars.next() {
+                Some((_, c)) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = match self.chars.next().map(|c| c.1) {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{C}',
+                        Some('u') => {
+                            let mut code = self.hex()?;
+                            if (0xD800..0xDC00).contains(&code) {
+                                if self.chars.next().map(|c| c.1) != Some('\\') ||
+                                    self.chars.next().map(|c| c.1) != Some('u')
+                                {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                let low = self.hex()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            match char::from_u32(code) {
+                                Some(c) => c,
+                                None => return Err(self.error("invalid unicode escape")),
+                            }
+                        }
+                        Some(c @ '"') | Some(c @ '\\') | Some(c @ '/') => c,
+                        Some(_) => return Err(self.error("invalid escape")),
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    s.push(escaped);
+                }
+                c if c < ' ' => return Err(self.error("unescaped control character")),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, VocabError> {
+        self.peek();
+        let mut value: u32 = 0;
+        let mut digits = 0;
+        while let Some(digit) = self.chars.peek().and_then(|c| c.1.to_digit(10)) {
+            self.chars.next();
+            digits += 1;
+            value = match value.checked_mul(10).and_then(|v| v.checked_add(digit)) {
+                Some(value) => value,
+                None => return Err(self.error("id out of range")),
+            };
+        }
+        if digits == 0 {
+            return Err(self.error("expected an id"));
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! An adapter for registering any filter as a tantivy tokenizer.
+//!
+//! # Examples
+//! ```
+//! extern crate tantivy;
+//! extern crate tokesies;
+//!
+//! use tantivy::schema::{Schema, TextFieldIndexing, TextOptions};
+//! use tantivy::Index;
+//! use tokesies::filters::WhitespaceFilter;
+//! use tokesies::tantivy_tokenizer::TantivyTokenizer;
+//!
+//! # fn main() {
+//! let mut schema = Schema::builder();
+//! let indexing = TextFieldIndexing::default().set_tokenizer("tokesies");
+//! schema.add_text_field("body", TextOptions::default().set_indexing_options(indexing));
+//!
+//! let index = Index::create_in_ram(schema.build());
+//! index.tokenizers().register("tokesies", TantivyTokenizer::new(WhitespaceFilter));
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use tantivy::tokenizer::{Token as TantivyToken, TokenStream, Tokenizer};
+
+use filters::Filter;
+use {FilteredTokenizer, TokenKind};
+
+/// A tantivy tokenizer that extracts tokens with a filter. Terms, positions
+/// and offsets are copied onto tantivy tokens, with offsets in bytes as
+/// tantivy expects.
+///
+/// The filter is shared between clones of the tokenizer, which tantivy makes
+/// for each thread that indexes or queries.
+pub struct TantivyTokenizer<T> {
+    filter: Arc<T>,
+}
+
+impl<T> TantivyTokenizer<T> {
+    pub fn new(filter: T) -> Self {
+        TantivyTokenizer {
+            filter: Arc::new(filter),
+        }
+    }
+}
+
+impl<T> Clone for TantivyTokenizer<T> {
+    fn clone(&self) -> Self {
+        TantivyTokenizer {
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+impl<T: Filter + Send + Sync + 'static> Tokenizer for TantivyTokenizer<T> {
+    type TokenStream<'a> = TantivyTokenStream<'a, T>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> TantivyTokenStream<'a, T> {
+        TantivyTokenStream {
+            tokenizer: FilteredTokenizer::new(&*self.filter, text),
+            token: TantivyToken::default(),
+        }
+    }
+}
+
+/// The tantivy token stream of a TantivyTokenizer.
+pub struct TantivyTokenStream<'a, T: Filter + 'a> {
+    tokenizer: FilteredTokenizer<'a, &'a T>,
+    token: TantivyToken,
+}
+
+impl<'a, T: Filter> TokenStream for TantivyTokenStream<'a, T> {
+    fn advance(&mut self) -> bool {
+        let token = loop {
+            match self.tokenizer.next() {
+                Some(token) if token.kind == TokenKind::Trivia => continue,
+                Some(token) => break token,
+                None => return false,
+            }
+        };
+        self.token.text.clear();
+        self.token.text.push_str(token.term());
+        self.token.offset_from = token.start_byte;
+        self.token.offset_to = token.end_byte;
+        self.token.position = token.position;
+        self.token.position_length = 1;
+        true
+    }
+
+    fn token(&self) -> &TantivyToken {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut TantivyToken {
+        &mut self.token
+    }
+}
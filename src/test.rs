@@ -21,6 +21,7 @@
 #[cfg(test)]
 mod tests {
     use ::*;
+    use token_filters::TokenFilterExt;
 
     #[test]
     fn simple_case() {
@@ -157,4 +158,1319 @@ mod tests {
             assert_eq!(exp.position, act.position);
         }
     }
+
+    #[test]
+    fn number_filter_case() {
+        let result: Vec<Token> = FilteredTokenizer::new(
+            filters::NumberFilter::default(),
+            "version 0.4 was -7.5% of 1,000,000 (1.2.3) and 6.02e23 or 5th-3",
+        ).collect::<Vec<Token>>();
+
+        let expected: Vec<(Token, TokenKind)> = vec![
+            (Token::from_str("version", 0, 0), TokenKind::Word),
+            (Token::from_str("0.4", 8, 1), TokenKind::Number),
+            (Token::from_str("was", 12, 2), TokenKind::Word),
+            (Token::from_str("-7.5%", 16, 3), TokenKind::Number),
+            (Token::from_str("of", 22, 4), TokenKind::Word),
+            (Token::from_str("1,000,000", 25, 5), TokenKind::Number),
+            (Token::from_str("(", 35, 6), TokenKind::Punctuation),
+            (Token::from_str("1.2.3", 36, 7), TokenKind::Number),
+            (Token::from_str(")", 41, 8), TokenKind::Punctuation),
+            (Token::from_str("and", 43, 9), TokenKind::Word),
+            (Token::from_str("6.02e23", 47, 10), TokenKind::Number),
+            (Token::from_str("or", 55, 11), TokenKind::Word),
+            (Token::from_str("5th", 58, 12), TokenKind::Word),
+            (Token::from_str("-", 61, 13), TokenKind::Punctuation),
+            (Token::from_str("3", 62, 14), TokenKind::Number),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, kind)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(kind, act.kind);
+        }
+    }
+
+    #[test]
+    fn url_filter_case() {
+        let filter = filters::UrlFilter {
+            hostnames: true,
+            ..filters::UrlFilter::new(filters::NumberFilter::default())
+        };
+        let result: Vec<Token> = FilteredTokenizer::new(
+            filter,
+            "see https://example.com/a?b=c, mail ash@jeffs.eu from 10.0.0.1 or ::1 at jeffs.eu 0.4",
+        ).collect::<Vec<Token>>();
+
+        let expected: Vec<(Token, TokenKind)> = vec![
+            (Token::from_str("see", 0, 0), TokenKind::Word),
+            (Token::from_str("https://example.com/a?b=c", 4, 1), TokenKind::Url),
+            (Token::from_str(",", 29, 2), TokenKind::Word),
+            (Token::from_str("mail", 31, 3), TokenKind::Word),
+            (Token::from_str("ash@jeffs.eu", 36, 4), TokenKind::Email),
+            (Token::from_str("from", 49, 5), TokenKind::Word),
+            (Token::from_str("10.0.0.1", 54, 6), TokenKind::IpAddress),
+            (Token::from_str("or", 63, 7), TokenKind::Word),
+            (Token::from_str("::1", 66, 8), TokenKind::IpAddress),
+            (Token::from_str("at", 70, 9), TokenKind::Word),
+            (Token::from_str("jeffs.eu", 73, 10), TokenKind::Hostname),
+            (Token::from_str("0.4", 82, 11), TokenKind::Number),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, kind)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(kind, act.kind);
+        }
+
+        let input = "open file.txt in main.rs, set self.value to end.The :: a::b std::io fe80::1";
+        let result: Vec<(String, TokenKind)> =
+            FilteredTokenizer::new(filters::UrlFilter::default(), input)
+                .filter(|t| t.kind != TokenKind::Word && t.kind != TokenKind::Punctuation)
+                .map(|t| (t.term().to_string(), t.kind))
+                .collect();
+        assert_eq!(vec![("fe80::1".to_string(), TokenKind::IpAddress)], result);
+    }
+
+    #[test]
+    fn url_component_filter_case() {
+        let result: Vec<Token> = FilteredTokenizer::new(
+            filters::UrlFilter::default(),
+            "go https://[::1]:8080/a-b?c=d then ash@jeffs.eu",
+        ).filter_tokens(token_filters::UrlComponentFilter {})
+            .collect::<Vec<Token>>();
+
+        let expected: Vec<(Token, TokenKind)> = vec![
+            (Token::from_str("go", 0, 0), TokenKind::Word),
+            (Token::from_str("https://[::1]:8080/a-b?c=d", 3, 1), TokenKind::Url),
+            (Token::from_str("https", 3, 1), TokenKind::Word),
+            (Token::from_str("::1", 12, 1), TokenKind::IpAddress),
+            (Token::from_str("8080", 17, 1), TokenKind::Number),
+            (Token::from_str("a", 22, 1), TokenKind::Word),
+            (Token::from_str("b", 24, 1), TokenKind::Word),
+            (Token::from_str("c", 26, 1), TokenKind::Word),
+            (Token::from_str("d", 28, 1), TokenKind::Word),
+            (Token::from_str("then", 30, 2), TokenKind::Word),
+            (Token::from_str("ash@jeffs.eu", 35, 3), TokenKind::Email),
+            (Token::from_str("ash", 35, 3), TokenKind::Word),
+            (Token::from_str("jeffs.eu", 39, 3), TokenKind::Hostname),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, kind)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(kind, act.kind);
+        }
+    }
+
+    #[test]
+    fn social_filter_case() {
+        let result: Vec<Token> = FilteredTokenizer::new(
+            filters::SocialFilter::default(),
+            "hi @jeffail!👋🏽 #rustlang is 👩\u{200D}💻 in 🇬🇧 C# 1\u{FE0F}\u{20E3}",
+        ).collect::<Vec<Token>>();
+
+        let expected: Vec<(Token, TokenKind)> = vec![
+            (Token::from_str("hi", 0, 0), TokenKind::Word),
+            (Token::from_str("@jeffail", 3, 1), TokenKind::Mention),
+            (Token::from_str("!", 11, 2), TokenKind::Punctuation),
+            (Token::from_str("👋🏽", 12, 3), TokenKind::Emoji),
+            (Token::from_str("#rustlang", 15, 4), TokenKind::Hashtag),
+            (Token::from_str("is", 25, 5), TokenKind::Word),
+            (Token::from_str("👩\u{200D}💻", 28, 6), TokenKind::Emoji),
+            (Token::from_str("in", 32, 7), TokenKind::Word),
+            (Token::from_str("🇬🇧", 35, 8), TokenKind::Emoji),
+            (Token::from_str("C", 38, 9), TokenKind::Word),
+            (Token::from_str("#", 39, 10), TokenKind::Punctuation),
+            (Token::from_str("1\u{FE0F}\u{20E3}", 41, 11), TokenKind::Emoji),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, kind)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(kind, act.kind);
+        }
+    }
+
+    #[test]
+    fn identifier_split_filter_case() {
+        let result: Vec<Token> = FilteredTokenizer::new(
+            filters::WhitespaceFilter {},
+            "call parseHTTPResponse2 on some_text now",
+        ).filter_tokens(token_filters::IdentifierSplitFilter {
+            preserve_original: true,
+            ..Default::default()
+        })
+            .collect::<Vec<Token>>();
+
+        let expected: Vec<(Token, TokenKind)> = vec![
+            (Token::from_str("call", 0, 0), TokenKind::Word),
+            (Token::from_str("parseHTTPResponse2", 5, 1), TokenKind::Word),
+            (Token::from_str("parse", 5, 1), TokenKind::Word),
+            (Token::from_str("HTTP", 10, 2), TokenKind::Word),
+            (Token::from_str("Response", 14, 3), TokenKind::Word),
+            (Token::from_str("2", 22, 4), TokenKind::Number),
+            (Token::from_str("on", 24, 5), TokenKind::Word),
+            (Token::from_str("some_text", 27, 6), TokenKind::Word),
+            (Token::from_str("some", 27, 6), TokenKind::Word),
+            (Token::from_str("text", 32, 7), TokenKind::Word),
+            (Token::from_str("now", 37, 8), TokenKind::Word),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, kind)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(kind, act.kind);
+        }
+
+        let result: Vec<(String, usize)> =
+            FilteredTokenizer::new(filters::WhitespaceFilter {}, "a __ _ b_c")
+                .filter_tokens(token_filters::IdentifierSplitFilter::default())
+                .map(|t| (t.term().to_string(), t.position))
+                .collect();
+        let expected: Vec<(String, usize)> = vec![
+            ("a".to_string(), 0),
+            ("__".to_string(), 1),
+            ("_".to_string(), 2),
+            ("b".to_string(), 3),
+            ("c".to_string(), 4),
+        ];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn quote_filter_case() {
+        let input = "say \"hello world\" it's \u{201C}curly\u{201D} 'it\\'s' \"open";
+
+        let result: Vec<Token> = FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+            .collect::<Vec<Token>>();
+
+        let expected: Vec<(Token, TokenKind)> = vec![
+            (Token::from_str("say", 0, 0), TokenKind::Word),
+            (Token::from_str("\"hello world\"", 4, 1), TokenKind::Quoted),
+            (Token::from_str("it", 18, 2), TokenKind::Word),
+            (Token::from_str("'", 20, 3), TokenKind::Punctuation),
+            (Token::from_str("s", 21, 4), TokenKind::Word),
+            (Token::from_str("\u{201C}curly\u{201D}", 23, 5), TokenKind::Quoted),
+            (Token::from_str("'it\\'s'", 31, 6), TokenKind::Quoted),
+            (Token::from_str("\"open", 39, 7), TokenKind::UnterminatedQuote),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, kind)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(kind, act.kind);
+        }
+
+        let filter = filters::QuoteFilter {
+            strip_quotes: true,
+            unescape: true,
+            ..Default::default()
+        };
+
+        let terms: Vec<String> = FilteredTokenizer::new(filter, input)
+            .map(|t| t.term().to_string())
+            .collect();
+        assert_eq!(
+            vec!["say", "hello world", "it", "'", "s", "curly", "it's", "open"],
+            terms
+        );
+
+        let terms: Vec<String> =
+            FilteredTokenizer::new(filters::QuoteFilter::default(), "'don't go' now")
+                .map(|t| t.term().to_string())
+                .collect();
+        assert_eq!(vec!["'don't go'", "now"], terms);
+
+        let input = "era = the \"90s\nkey = \"value\"\nport = 80";
+        let result: Vec<(String, TokenKind)> =
+            FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+                .map(|t| (t.term().to_string(), t.kind))
+                .collect();
+        assert_eq!(("\"90s".to_string(), TokenKind::UnterminatedQuote), result[3]);
+        assert_eq!(("\"value\"".to_string(), TokenKind::Quoted), result[6]);
+        assert_eq!(("80".to_string(), TokenKind::Word), result[9]);
+
+        let terms: Vec<String> =
+            FilteredTokenizer::new(filters::QuoteFilter::default(), "'tis fine 'twas\nok")
+                .map(|t| t.term().to_string())
+                .collect();
+        assert_eq!(vec!["'", "tis", "fine", "'", "twas", "ok"], terms);
+
+        let terms: Vec<String> =
+            FilteredTokenizer::new(filters::QuoteFilter::default(), "a\u{301}'b' c")
+                .by_graphemes()
+                .map(|t| t.term().to_string())
+                .collect();
+        assert_eq!(vec!["a\u{301}", "'", "b", "'", "c"], terms);
+
+        let filter = filters::QuoteFilter {
+            strip_quotes: true,
+            ..Default::default()
+        };
+        let input = "say \"fooBar\"";
+        let result: Vec<Token> = FilteredTokenizer::new(filter, input)
+            .track_lines(4)
+            .filter_tokens(token_filters::IdentifierSplitFilter::default())
+            .collect();
+        let spans: Vec<(&str, usize, usize, usize)> = result
+            .iter()
+            .map(|t| (t.term(), t.start_byte, t.start_offset, t.location.unwrap().column))
+            .collect();
+        assert_eq!(vec![("say", 0, 0, 1), ("foo", 5, 5, 6), ("Bar", 8, 8, 9)], spans);
+        for token in &result {
+            assert_eq!(&input[token.start_byte..token.end_byte], token.term());
+        }
+
+        let filter = filters::QuoteFilter {
+            strip_quotes: true,
+            unescape: true,
+            ..Default::default()
+        };
+        let spans: Vec<(String, usize, usize)> = FilteredTokenizer::new(filter, "x \"a\\\"bC\"")
+            .filter_tokens(token_filters::IdentifierSplitFilter::default())
+            .map(|t| (t.term().to_string(), t.start_byte, t.end_byte))
+            .collect();
+        assert_eq!(
+            vec![("x".to_string(), 0, 1), ("a\"b".to_string(), 2, 9), ("C".to_string(), 2, 9)],
+            spans
+        );
+    }
+
+    #[test]
+    fn sentence_tokenizer_case() {
+        let input = "Dr. Dobb's costs 0.4 e.g. in Rust. Wait... Really? Yes!";
+        let sentences: Vec<sentences::Sentence> = sentences::SentenceTokenizer::new(input)
+            .collect();
+
+        let expected = [
+            ("Dr. Dobb's costs 0.4 e.g. in Rust.", 0),
+            ("Wait...", 35),
+            ("Really?", 43),
+            ("Yes!", 51),
+        ];
+
+        assert_eq!(expected.len(), sentences.len());
+        for (i, &(text, offset)) in expected.iter().enumerate() {
+            let act = sentences.get(i).unwrap();
+            assert_eq!(text, act.text);
+            assert_eq!(offset, act.char_offset);
+            assert_eq!(offset, act.byte_offset);
+        }
+
+        let cases: [(&str, &[&str]); 4] = [
+            ("I said no. Then I left.", &["I said no.", "Then I left."]),
+            ("It was in Mar. It rained.", &["It was in Mar.", "It rained."]),
+            ("See No. 5 by Smith et al. in the list.", &["See No. 5 by Smith et al. in the list."]),
+            ("Visit St. paul on Mar. 3.", &["Visit St. paul on Mar. 3."]),
+        ];
+        for &(input, expected) in &cases {
+            let texts: Vec<&str> = sentences::SentenceTokenizer::new(input)
+                .map(|s| s.text)
+                .collect();
+            assert_eq!(expected, &texts[..]);
+        }
+
+        let filter = filters::DefaultFilter {};
+        let mut result: Vec<Token> = Vec::new();
+        let mut position = 0;
+        for sentence in sentences.iter().skip(2) {
+            let mut tokens = sentence.tokenize(&filter, position);
+            result.extend(&mut tokens);
+            position = tokens.position();
+        }
+
+        let expected: Vec<Token> = vec![
+            Token::from_str("Really", 43, 0),
+            Token::from_str("?", 49, 1),
+            Token::from_str("Yes", 51, 2),
+            Token::from_str("!", 54, 3),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+        }
+    }
+
+    #[test]
+    fn graphemes_case() {
+        let input = "cafe\u{301} !\u{301}ok 👩\u{200D}💻x";
+
+        let result: Vec<Token> = FilteredTokenizer::new(filters::DefaultFilter {}, input)
+            .collect::<Vec<Token>>();
+        assert_eq!(
+            vec!["cafe\u{301}", "!", "\u{301}ok", "👩\u{200D}💻x"],
+            result.iter().map(|t| t.term()).collect::<Vec<&str>>()
+        );
+
+        let result: Vec<Token> = FilteredTokenizer::new(filters::SocialFilter::default(), input)
+            .by_graphemes()
+            .collect::<Vec<Token>>();
+
+        let expected: Vec<Token> = vec![
+            Token::from_str("cafe\u{301}", 0, 0),
+            Token::from_str("!\u{301}", 5, 1),
+            Token::from_str("ok", 6, 2),
+            Token::from_str("👩\u{200D}💻", 9, 3),
+            Token::from_str("x", 10, 4),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+        }
+    }
+
+    #[test]
+    fn offset_unit_case() {
+        let input = "a😀 é\u{301}b x";
+        let expected = [
+            (OffsetUnit::Bytes, [(0, 5), (6, 11), (12, 13)]),
+            (OffsetUnit::Chars, [(0, 2), (3, 6), (7, 8)]),
+            (OffsetUnit::Utf16, [(0, 3), (4, 7), (8, 9)]),
+            (OffsetUnit::Graphemes, [(0, 2), (3, 5), (6, 7)]),
+        ];
+
+        for &(unit, ref offsets) in expected.iter() {
+            let result: Vec<Token> = FilteredTokenizer::new(filters::WhitespaceFilter {}, input)
+                .offset_unit(unit)
+                .collect::<Vec<Token>>();
+
+            assert_eq!(offsets.len(), result.len());
+            for (i, &(start, end)) in offsets.iter().enumerate() {
+                let act = result.get(i).unwrap();
+                assert_eq!(start, act.start_offset);
+                assert_eq!(end, act.end_offset);
+                assert_eq!(unit, act.offset_unit);
+            }
+        }
+
+        let input = "a !\u{301}ok b";
+        let forward: Vec<(usize, usize)> = FilteredTokenizer::new(filters::DefaultFilter {}, input)
+            .offset_unit(OffsetUnit::Graphemes)
+            .map(|t| (t.start_offset, t.end_offset))
+            .collect();
+        let mut reverse: Vec<(usize, usize)> =
+            FilteredTokenizer::new(filters::DefaultFilter {}, input)
+                .offset_unit(OffsetUnit::Graphemes)
+                .rev()
+                .map(|t| (t.start_offset, t.end_offset))
+                .collect();
+        reverse.reverse();
+        assert_eq!(forward, reverse);
+        assert_eq!(Some(&(6, 7)), forward.last());
+    }
+
+    #[test]
+    fn track_lines_case() {
+        let result: Vec<Token> = FilteredTokenizer::new(
+            filters::DefaultFilter {},
+            "key = a\r\n\tval\rnext\n\nx\ty",
+        ).track_lines(4)
+            .collect::<Vec<Token>>();
+
+        let expected = [
+            ("key", 1, 1),
+            ("=", 1, 5),
+            ("a", 1, 7),
+            ("val", 2, 5),
+            ("next", 3, 1),
+            ("x", 5, 1),
+            ("y", 5, 5),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(term, line, column)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(term, act.term());
+            assert_eq!(Some(LineColumn { line, column }), act.location);
+        }
+    }
+
+    #[test]
+    fn lossless_case() {
+        let input = "  hello, \"big\"\tworld!\n";
+        let result: Vec<Token> = FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+            .lossless()
+            .collect::<Vec<Token>>();
+
+        let expected: Vec<(Token, TokenKind)> = vec![
+            (Token::from_str("  ", 0, 0), TokenKind::Trivia),
+            (Token::from_str("hello,", 2, 0), TokenKind::Word),
+            (Token::from_str(" ", 8, 1), TokenKind::Trivia),
+            (Token::from_str("\"big\"", 9, 1), TokenKind::Quoted),
+            (Token::from_str("\t", 14, 2), TokenKind::Trivia),
+            (Token::from_str("world", 15, 2), TokenKind::Word),
+            (Token::from_str("!", 20, 3), TokenKind::Punctuation),
+            (Token::from_str("\n", 21, 4), TokenKind::Trivia),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, kind)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(kind, act.kind);
+        }
+
+        let rebuilt: String = result.iter().map(|t| t.term()).collect();
+        assert_eq!(input, rebuilt);
+
+        let scanning: Vec<Box<dyn filters::Filter>> = vec![
+            Box::new(filters::NumberFilter::default()),
+            Box::new(filters::UrlFilter::default()),
+            Box::new(filters::SocialFilter::default()),
+            Box::new(filters::QuoteFilter::default()),
+        ];
+        for filter in &scanning {
+            for input in &["a ", "#tag 42 http://a.b/c \"q\" @me\t\n", " "] {
+                let rebuilt: String = FilteredTokenizer::new(filter, input)
+                    .lossless()
+                    .map(|t| t.term().to_string())
+                    .collect();
+                assert_eq!(*input, rebuilt);
+            }
+        }
+    }
+
+    #[test]
+    fn detokenizer_case() {
+        let input = "Hello  John. (it is \"far\")\tnow!";
+        let mut tokens: Vec<Token> = FilteredTokenizer::new(filters::DefaultFilter {}, input)
+            .collect();
+        assert_eq!(
+            detokenizer::Detokenizer::new(input).detokenize(&tokens),
+            input
+        );
+
+        tokens[1].term = "[MASK]".into();
+        tokens.remove(5);
+        tokens.insert(2, Token::from_str("said", 0, 0));
+        assert_eq!(
+            detokenizer::Detokenizer::new(input).detokenize(&tokens),
+            "Hello  [MASK] said. (it \"far\")\tnow!"
+        );
+
+        let terms = [
+            "Hello", "(", "it", "is", "n't", "\"", "far", "\"", ")", ",", "你", "好", ".",
+        ];
+        let tokens: Vec<Token> = terms.iter().map(|t| Token::from_str(t, 0, 0)).collect();
+        assert_eq!(
+            detokenizer::Detokenizer::without_input().detokenize(&tokens),
+            "Hello (it isn't \"far\"), 你好."
+        );
+    }
+
+    #[test]
+    fn double_ended_case() {
+        let input = "hello! !world 0.4 this!is  some text ";
+        let forward: Vec<Token> =
+            FilteredTokenizer::new(filters::NumberFilter::default(), input).collect();
+        let backward: Vec<Token> =
+            FilteredTokenizer::new(filters::NumberFilter::default(), input).rev().collect();
+
+        assert_eq!(forward.len(), backward.len());
+        for (i, (exp, act)) in forward.iter().rev().zip(backward.iter()).enumerate() {
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.end_offset, act.end_offset);
+            assert_eq!(exp.kind, act.kind);
+            assert_eq!(i, act.position);
+        }
+
+        let input = "say \"hello world\" ok 'a b' end";
+        let forward: Vec<String> = FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+            .map(|t| t.term().to_string())
+            .collect();
+        let mut backward: Vec<String> =
+            FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+                .rev()
+                .map(|t| t.term().to_string())
+                .collect();
+        backward.reverse();
+        assert_eq!(vec!["say", "\"hello world\"", "ok", "'a b'", "end"], forward);
+        assert_eq!(forward, backward);
+
+        let mut tokenizer = FilteredTokenizer::new(filters::QuoteFilter::default(), input);
+        assert_eq!(Some("say"), tokenizer.next().as_ref().map(|t| t.term()));
+        assert_eq!(Some("end"), tokenizer.next_back().as_ref().map(|t| t.term()));
+        let middle: Vec<String> = tokenizer.map(|t| t.term().to_string()).collect();
+        assert_eq!(vec!["\"hello world\"", "ok", "'a b'"], middle);
+
+        // Filters that only classify chars are tokenized lazily from the back.
+        struct SpaceFilter;
+        impl filters::Filter for SpaceFilter {
+            fn on_char(&self, c: &char) -> (bool, bool) {
+                (*c == ' ', false)
+            }
+        }
+        let mut tokenizer = FilteredTokenizer::new(SpaceFilter, "a b c");
+        assert_eq!(Some("c"), tokenizer.next_back().as_ref().map(|t| t.term()));
+        assert!(tokenizer.back.is_empty());
+
+        let input = "hello! !world 0.4 this!is  some text ";
+        let mut tokenizer = FilteredTokenizer::new(filters::DefaultFilter {}, input);
+        let mut result: Vec<Token> = Vec::new();
+        result.push(tokenizer.next().unwrap());
+        result.push(tokenizer.next_back().unwrap());
+        result.push(tokenizer.next_back().unwrap());
+        result.extend(&mut tokenizer);
+        assert_eq!(tokenizer.next_back().map(|t| t.start_offset), None);
+
+        let expected: Vec<Token> = vec![
+            Token::from_str("hello", 0, 0),
+            Token::from_str("text", 32, 0),
+            Token::from_str("some", 27, 1),
+            Token::from_str("!", 5, 1),
+            Token::from_str("!", 7, 2),
+            Token::from_str("world", 8, 3),
+            Token::from_str("0", 14, 4),
+            Token::from_str(".", 15, 5),
+            Token::from_str("4", 16, 6),
+            Token::from_str("this", 18, 7),
+            Token::from_str("!", 22, 8),
+            Token::from_str("is", 23, 9),
+        ];
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+        }
+    }
+
+    #[test]
+    fn tokenizer_state_case() {
+        let input = "héllo wörld, this is some text";
+        let mut tokenizer = FilteredTokenizer::new(filters::DefaultFilter {}, input);
+        tokenizer.next();
+        tokenizer.next();
+        let state = tokenizer.state();
+        let expected: Vec<Token> = tokenizer.collect();
+
+        let result: Vec<Token> = FilteredTokenizer::with_state(
+            filters::DefaultFilter {},
+            input,
+            state.byte_offset,
+            state.char_offset,
+            state.position,
+        ).collect();
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+        }
+
+        let mut tokenizer = FilteredTokenizer::new(filters::DefaultFilter {}, input);
+        assert_eq!(tokenizer.seek_to(2), 6);
+        assert_eq!(
+            tokenizer.next().map(|t| (t.term().to_string(), t.start_offset)),
+            Some(("wörld,".to_string(), 6))
+        );
+        assert_eq!(tokenizer.seek_to(20), 20);
+        assert_eq!(
+            tokenizer.next().map(|t| (t.term().to_string(), t.start_offset)),
+            Some(("is".to_string(), 18))
+        );
+        assert_eq!(tokenizer.seek_to(0), 0);
+        assert_eq!(tokenizer.next().map(|t| t.start_offset), Some(0));
+
+        let input = "pi 3.14159 x";
+        let mut tokenizer = FilteredTokenizer::new(filters::NumberFilter::default(), input);
+        assert_eq!(tokenizer.seek_to(5), 10);
+        assert_eq!(tokenizer.next().map(|t| t.term().to_string()), Some("x".to_string()));
+        assert_eq!(tokenizer.seek_to(3), 3);
+        assert_eq!(
+            tokenizer.next().map(|t| t.term().to_string()),
+            Some("3.14159".to_string())
+        );
+
+        let input = "a\ngo https://example.com/a now";
+        let mut tokenizer = FilteredTokenizer::new(filters::UrlFilter::default(), input);
+        assert_eq!(tokenizer.seek_to(15), 26);
+        assert_eq!(tokenizer.next().map(|t| t.term().to_string()), Some("now".to_string()));
+
+        let input = "a  \t b 1.5   c";
+        let mut tokenizer = FilteredTokenizer::new(filters::DefaultFilter {}, input).lossless();
+        assert_eq!(tokenizer.seek_to(2), 5);
+        assert_eq!(tokenizer.next().map(|t| t.term().to_string()), Some("b".to_string()));
+        let mut tokenizer =
+            FilteredTokenizer::new(filters::NumberFilter::default(), input).lossless();
+        assert_eq!(tokenizer.seek_to(11), 13);
+        assert_eq!(tokenizer.next().map(|t| t.term().to_string()), Some("c".to_string()));
+        assert_eq!(tokenizer.seek_to(10), 10);
+        assert_eq!(
+            tokenizer.next().map(|t| (t.term().to_string(), t.kind)),
+            Some(("   ".to_string(), TokenKind::Trivia))
+        );
+        let mut tokenizer = FilteredTokenizer::new(filters::NumberFilter::default(), "1\n  x")
+            .lossless();
+        assert_eq!(tokenizer.seek_to(2), 4);
+    }
+
+    #[test]
+    fn retokenize_case() {
+        let old_input = "hello wörld, this\nis some text";
+        let new_input = "hello big wide, this\nis some text";
+        let previous: Vec<Token> = FilteredTokenizer::new(filters::DefaultFilter {}, old_input)
+            .track_lines(4)
+            .collect();
+
+        let result = incremental::retokenize(
+            &previous,
+            new_input,
+            incremental::Edit {
+                start: 6,
+                old_end: 12,
+                new_end: 14,
+            },
+            |input| FilteredTokenizer::new(filters::DefaultFilter {}, input).track_lines(4),
+        );
+        assert_eq!(1..2, result.removed);
+        assert_eq!(1..3, result.inserted);
+
+        let expected: Vec<Token> = FilteredTokenizer::new(filters::DefaultFilter {}, new_input)
+            .track_lines(4)
+            .collect();
+
+        assert_eq!(expected.len(), result.tokens.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.tokens.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.end_offset, act.end_offset);
+            assert_eq!(exp.start_byte, act.start_byte);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(exp.location, act.location);
+        }
+    }
+
+    #[test]
+    fn retokenize_scanning_case() {
+        let cases = [
+            ("pi is 3.x", 8, 9, "14"),
+            ("pi is 3. 5", 8, 9, ""),
+            ("see a\nhttp:x.y now", 11, 11, "//"),
+            ("ip 10.0.0.1 ok", 10, 11, "x"),
+        ];
+        for &(old_input, start, old_end, text) in &cases {
+            let new_input = format!("{}{}{}", &old_input[..start], text, &old_input[old_end..]);
+            let tokenizer = |input| {
+                let filter = filters::UrlFilter::new(filters::NumberFilter::default());
+                FilteredTokenizer::new(filter, input)
+            };
+            let previous: Vec<Token> = tokenizer(old_input).collect();
+            let result = incremental::retokenize(
+                &previous,
+                &new_input,
+                incremental::Edit {
+                    start,
+                    old_end,
+                    new_end: start + text.len(),
+                },
+                tokenizer,
+            );
+            let expected: Vec<Token> = tokenizer(&new_input).collect();
+
+            assert_eq!(expected.len(), result.tokens.len(), "{:?}", new_input);
+            for (exp, act) in expected.iter().zip(&result.tokens) {
+                assert_eq!(exp.term(), act.term());
+                assert_eq!(exp.kind, act.kind);
+                assert_eq!(exp.start_offset, act.start_offset);
+                assert_eq!(exp.position, act.position);
+            }
+        }
+    }
+
+    #[test]
+    fn retokenize_gap_case() {
+        fn tokenizer<'a>(
+            input: &'a str,
+            graphemes: bool,
+        ) -> FilteredTokenizer<'a, filters::QuoteFilter> {
+            let tokenizer = FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+                .offset_unit(OffsetUnit::Utf16)
+                .track_lines(4);
+            if graphemes {
+                tokenizer.by_graphemes()
+            } else {
+                tokenizer
+            }
+        }
+
+        let cases = [
+            ("ab   cd ef", 3, 4, "", false),
+            ("\tab cd", 0, 1, "", false),
+            ("  ab cd", 0, 0, "x", false),
+            ("ab cd", 0, 2, "", false),
+            ("ab\rcd ef", 3, 3, "\n", false),
+            ("ab\r\n\tcd ef", 3, 4, "", false),
+            ("ab\tcd\tef\r\ngh", 2, 2, "x", false),
+            ("wö\r\n  rld \"a b\" c", 5, 6, "日", false),
+            ("ab   cd ef", 3, 4, "", true),
+            ("a, \u{1F44D}\u{1F3FD}\u{1F44D}\u{1F3FD}", 3, 7, "", true),
+        ];
+        for &(old_input, start, old_end, text, graphemes) in &cases {
+            let new_input = format!("{}{}{}", &old_input[..start], text, &old_input[old_end..]);
+            let previous: Vec<Token> = tokenizer(old_input, graphemes).collect();
+            let result = incremental::retokenize(
+                &previous,
+                &new_input,
+                incremental::Edit {
+                    start,
+                    old_end,
+                    new_end: start + text.len(),
+                },
+                |input| tokenizer(input, graphemes),
+            );
+            let expected: Vec<Token> = tokenizer(&new_input, graphemes).collect();
+
+            assert_eq!(expected.len(), result.tokens.len(), "{:?}", new_input);
+            for (exp, act) in expected.iter().zip(&result.tokens) {
+                assert_eq!(exp.term(), act.term());
+                assert_eq!(exp.start_offset, act.start_offset);
+                assert_eq!(exp.end_offset, act.end_offset);
+                assert_eq!(exp.start_byte, act.start_byte);
+                assert_eq!(exp.position, act.position);
+                assert_eq!(exp.location, act.location);
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_case() {
+        let input = "hello wörld, \"this is\" some\ntext 日本語  with  a\tfew more wörds \
+                     \"and a quote\" at the end\n\nof the   input";
+
+        let parallel = parallel::ParallelTokenizer::new(|input| {
+            FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+                .offset_unit(OffsetUnit::Utf16)
+                .track_lines(4)
+        })
+        .chunk_size(7)
+        .threads(3);
+        let result = parallel.tokenize(input);
+
+        let expected: Vec<Token> = FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+            .offset_unit(OffsetUnit::Utf16)
+            .track_lines(4)
+            .collect();
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.end_offset, act.end_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(exp.kind, act.kind);
+            assert_eq!(exp.location, act.location);
+        }
+
+        let lossless = parallel::ParallelTokenizer::new(|input| {
+            FilteredTokenizer::new(filters::DefaultFilter {}, input).lossless()
+        })
+        .chunk_size(5);
+        let result = lossless.tokenize(input);
+        let expected: Vec<Token> = FilteredTokenizer::new(filters::DefaultFilter {}, input)
+            .lossless()
+            .collect();
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(exp.kind, act.kind);
+        }
+
+        // A quote overrunning many chunks is tokenized once more from its start
+        // rather than once for every chunk it overruns.
+        let input = format!("a \"{}", "bc d, ".repeat(200));
+        let tokenizers = std::sync::atomic::AtomicUsize::new(0);
+        let quotes = parallel::ParallelTokenizer::new(|input| {
+            tokenizers.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            FilteredTokenizer::new(filters::QuoteFilter::default(), input)
+                .offset_unit(OffsetUnit::Utf16)
+                .lossless()
+        })
+        .chunk_size(8);
+        let result = quotes.tokenize(&input);
+        let expected: Vec<Token> = FilteredTokenizer::new(filters::QuoteFilter::default(), &input)
+            .offset_unit(OffsetUnit::Utf16)
+            .lossless()
+            .collect();
+
+        assert_eq!(expected.len(), result.len());
+        for (exp, act) in expected.iter().zip(&result) {
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(exp.kind, act.kind);
+        }
+        assert!(tokenizers.into_inner() < input.len() / 4);
+    }
+
+    #[test]
+    fn ascii_table_case() {
+        use filters::Filter;
+
+        let default = filters::DefaultFilter {};
+        let whitespace = filters::WhitespaceFilter {};
+        let default_table = default.ascii_table().unwrap();
+        let whitespace_table = whitespace.ascii_table().unwrap();
+        for b in 0..128u8 {
+            let c = b as char;
+            assert_eq!(Some(default.on_char(&c)), default_table.class(b));
+            assert_eq!(Some(whitespace.on_char(&c)), whitespace_table.class(b));
+        }
+        assert!(default.ascii_table() == Some(&filters::AsciiTable::from_filter(&default)));
+        assert_eq!(None, default.ascii_table().unwrap().class(0xc3));
+
+        let input = "The quick brown fox jumps over the lazy dog, twice; then \
+                     the naïve fox (named \u{201C}Zoë\u{201D}) jumped_over_a_very_long_log_again! \
+                     aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\tend";
+        for start in 0..input.len() {
+            let bytes = &input.as_bytes()[start..];
+            let table = default.ascii_table().unwrap();
+            let expected = bytes
+                .iter()
+                .position(|b| table.class(*b) != Some((false, false)))
+                .unwrap_or(bytes.len());
+            assert_eq!(expected, ascii::word_len(table, bytes));
+        }
+
+        let hash_filter = || filters::HashFilter {
+            drop_chars: [' ', '\t'].iter().cloned().collect(),
+            keep_chars: [',', ';', '(', ')', '_', '\u{201C}'].iter().cloned().collect(),
+        };
+        let expected: Vec<Token> = FilteredTokenizer::new(hash_filter(), input).collect();
+        let result: Vec<Token> =
+            FilteredTokenizer::new(filters::TableFilter::new(hash_filter()), input).collect();
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_case() {
+        extern crate serde_json;
+
+        use std::borrow::Cow;
+
+        let input = "hello wörld, this is some_text";
+        let tokens: Vec<Token> = FilteredTokenizer::new(filters::DefaultFilter {}, input)
+            .track_lines(4)
+            .collect();
+        let json = serde_json::to_string(&tokens).unwrap();
+        let result: Vec<Token> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(tokens.len(), result.len());
+        for (i, exp) in tokens.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert!(matches!(act.term, Cow::Borrowed(_)));
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.end_byte, act.end_byte);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(exp.kind, act.kind);
+            assert_eq!(exp.location, act.location);
+        }
+
+        let filter: filters::HashFilter =
+            serde_json::from_str(r#"{"keep_chars": [","], "drop_chars": [" "]}"#).unwrap();
+        let terms: Vec<String> = FilteredTokenizer::new(filter, input)
+            .map(|t| t.term().to_string())
+            .collect();
+        assert_eq!(vec!["hello", "wörld", ",", "this", "is", "some_text"], terms);
+
+        let config: config::PipelineConfig = serde_json::from_str(
+            r#"{
+                "filter": {"type": "url", "inner": {"type": "custom", "keep": ",", "drop": " "}},
+                "offset_unit": "utf16",
+                "token_filters": [
+                    {"type": "url_components"},
+                    {"type": "identifier_split", "preserve_original": true}
+                ]
+            }"#,
+        ).unwrap();
+        assert_eq!(Some(OffsetUnit::Utf16), config.offset_unit);
+        let pipeline = config.build();
+
+        let input = "see https://example.com/a, then some_text";
+        let expected: Vec<Token> = FilteredTokenizer::new(
+            filters::UrlFilter::new(filters::TableFilter::new(filters::HashFilter {
+                keep_chars: [','].iter().cloned().collect(),
+                drop_chars: [' '].iter().cloned().collect(),
+            })),
+            input,
+        ).offset_unit(OffsetUnit::Utf16)
+            .filter_tokens(token_filters::UrlComponentFilter)
+            .filter_tokens(token_filters::IdentifierSplitFilter {
+                preserve_original: true,
+                ..Default::default()
+            })
+            .collect();
+        let result: Vec<Token> = pipeline.tokenize(input).collect();
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(exp.kind, act.kind);
+        }
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(config, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn byte_tokenizer_case() {
+        use bytes::{ByteTokenizer, InvalidUtf8Error, InvalidUtf8Policy};
+
+        let input: &[u8] = b"hello w\xC3\xB6rld, ab\xFFcd \xE2\x82 end";
+
+        let expected = [
+            (Token::from_str("hello", 0, 0), TokenKind::Word, 0),
+            (Token::from_str("w\u{F6}rld,", 6, 1), TokenKind::Word, 6),
+            (Token::from_str("ab", 13, 2), TokenKind::Word, 14),
+            (Token::from_str("\u{FFFD}", 15, 3), TokenKind::InvalidUtf8, 16),
+            (Token::from_str("cd", 16, 4), TokenKind::Word, 17),
+            (Token::from_str("\u{FFFD}", 19, 5), TokenKind::InvalidUtf8, 20),
+            (Token::from_str("end", 21, 6), TokenKind::Word, 23),
+        ];
+        let result: Vec<Token> =
+            ByteTokenizer::new(filters::DefaultFilter {}, input, InvalidUtf8Policy::Replace)
+                .map(Result::unwrap)
+                .collect();
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, kind, start_byte)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.term());
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.position, act.position);
+            assert_eq!(kind, act.kind);
+            assert_eq!(start_byte, act.start_byte);
+        }
+
+        // Offsets agree with those of the lossy input when U+FFFD ends tokens.
+        let filter = |keep: &[char]| filters::HashFilter {
+            keep_chars: keep.iter().cloned().collect(),
+            drop_chars: [' '].iter().cloned().collect(),
+        };
+        let lossy = String::from_utf8_lossy(input);
+        let tokenized: Vec<Token> = FilteredTokenizer::new(filter(&['\u{FFFD}']), &lossy)
+            .offset_unit(OffsetUnit::Utf16)
+            .collect();
+        let result: Vec<Token> = ByteTokenizer::new(filter(&[]), input, InvalidUtf8Policy::Opaque)
+            .offset_unit(OffsetUnit::Utf16)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(tokenized.len(), result.len());
+        for (exp, act) in tokenized.iter().zip(result.iter()) {
+            assert_eq!(exp.start_offset, act.start_offset);
+            assert_eq!(exp.end_offset, act.end_offset);
+        }
+        assert_eq!("\\xFF", result[3].term());
+        assert_eq!("\\xE2\\x82", result[5].term());
+        assert_eq!(b"\xE2\x82", &input[result[5].start_byte..result[5].end_byte]);
+
+        let policy = InvalidUtf8Policy::Error;
+        let result: Vec<Result<Token, InvalidUtf8Error>> =
+            ByteTokenizer::new(filters::DefaultFilter {}, input, policy).collect();
+        assert_eq!(4, result.len());
+        assert_eq!(
+            Some(&InvalidUtf8Error {
+                byte_offset: 16,
+                len: 1,
+            }),
+            result[3].as_ref().err()
+        );
+    }
+
+    #[cfg(feature = "tantivy")]
+    #[test]
+    fn tantivy_tokenizer_case() {
+        use tantivy::collector::TopDocs;
+        use tantivy::query::{PhraseQuery, TermQuery};
+        use tantivy::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions};
+        use tantivy::tokenizer::{TextAnalyzer, TokenStream};
+        use tantivy::{doc, Index, Term};
+        use tantivy_tokenizer::TantivyTokenizer;
+
+        let input = "hello wörld, this is some_text";
+        let expected: Vec<Token> =
+            FilteredTokenizer::new(filters::DefaultFilter {}, input).collect();
+
+        let mut analyzer = TextAnalyzer::from(TantivyTokenizer::new(filters::DefaultFilter {}));
+        let mut stream = analyzer.token_stream(input);
+        let mut result = Vec::new();
+        while let Some(token) = stream.next() {
+            result.push(token.clone());
+        }
+
+        assert_eq!(expected.len(), result.len());
+        for (i, exp) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.text);
+            assert_eq!(exp.start_byte, act.offset_from);
+            assert_eq!(exp.end_byte, act.offset_to);
+            assert_eq!(exp.position, act.position);
+        }
+
+        let mut schema = Schema::builder();
+        let indexing = TextFieldIndexing::default()
+            .set_tokenizer("tokesies")
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let options = TextOptions::default().set_indexing_options(indexing);
+        let body = schema.add_text_field("body", options);
+        let index = Index::create_in_ram(schema.build());
+        index
+            .tokenizers()
+            .register("tokesies", TantivyTokenizer::new(filters::WhitespaceFilter));
+
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        writer.add_document(doc!(body => "call some_text now")).unwrap();
+        writer.add_document(doc!(body => "some text")).unwrap();
+        writer.commit().unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        let count = |query: &dyn tantivy::query::Query| {
+            searcher.search(query, &TopDocs::with_limit(10)).unwrap().len()
+        };
+        let term = |text: &str| Term::from_field_text(body, text);
+
+        let query = TermQuery::new(term("some_text"), IndexRecordOption::Basic);
+        assert_eq!(1, count(&query));
+        assert_eq!(1, count(&PhraseQuery::new(vec![term("some"), term("text")])));
+        assert_eq!(0, count(&PhraseQuery::new(vec![term("text"), term("some")])));
+    }
+
+    #[test]
+    fn wordpiece_case() {
+        let vocab = "[PAD]\n[UNK]\nun\n##aff\n##able\nthe\nw\u{F6}\n##r\n##ld\n,\n";
+        let mut model = wordpiece::WordPiece::from_vocab(vocab).unwrap();
+        model.max_chars_per_word = 10;
+
+        let input = "the unaffable w\u{F6}rld , unknown extraordinarily";
+        let expected = [
+            (Token::from_str("the", 0, 0), 5),
+            (Token::from_str("un", 4, 1), 2),
+            (Token::from_str("aff", 6, 2), 3),
+            (Token::from_str("able", 9, 3), 4),
+            (Token::from_str("w\u{F6}", 14, 4), 6),
+            (Token::from_str("r", 16, 5), 7),
+            (Token::from_str("ld", 17, 6), 8),
+            (Token::from_str(",", 20, 7), 9),
+            (Token::from_str("unknown", 22, 8), 1),
+            (Token::from_str("extraordinarily", 30, 9), 1),
+        ];
+        let result = model.encode(FilteredTokenizer::new(filters::WhitespaceFilter, input));
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, id)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.token.term());
+            assert_eq!(exp.start_offset, act.token.start_offset);
+            assert_eq!(exp.end_offset, act.token.end_offset);
+            assert_eq!(exp.position, act.token.position);
+            assert_eq!(id, act.id);
+        }
+        assert_eq!(Some(3), model.id("##aff"));
+
+        match wordpiece::WordPiece::from_vocab("a\nb\n") {
+            Err(subword::VocabError::MissingToken(ref token)) => assert_eq!("[UNK]", token),
+            _ => panic!("expected a missing token error"),
+        }
+    }
+
+    #[test]
+    fn bpe_case() {
+        use bpe::{Bpe, BpeLevel, BpeTrainer};
+
+        let vocab = r#"{"h": 0, "e": 1, "l": 2, "o": 3, "\u0120": 4, "w": 5, "r": 6, "d": 7,
+            "he": 8, "ll": 9, "hell": 10, "hello": 11, "\u0120w": 12}"#;
+        let merges = "#version: 0.2\nh e\nl l\nhe ll\nhell o\n\u{120} w\n";
+        let model = Bpe::from_vocab(vocab, merges, BpeLevel::Bytes).unwrap();
+
+        let input = "hello world";
+        let expected = [
+            (Token::from_str("hello", 0, 0), 11),
+            (Token::from_str(" w", 5, 1), 12),
+            (Token::from_str("o", 7, 2), 3),
+            (Token::from_str("r", 8, 3), 6),
+            (Token::from_str("l", 9, 4), 2),
+            (Token::from_str("d", 10, 5), 7),
+        ];
+        let result = model.encode(FilteredTokenizer::new(filters::DefaultFilter {}, input));
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, id)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.token.term());
+            assert_eq!(exp.start_offset, act.token.start_offset);
+            assert_eq!(exp.end_offset, act.token.end_offset);
+            assert_eq!(exp.position, act.token.position);
+            assert_eq!(id, act.id);
+        }
+        let ids: Vec<u32> = result.iter().map(|t| t.id).collect();
+        assert_eq!(input, model.decode(&ids));
+
+        match Bpe::from_vocab(vocab, "h e\nx y\n", BpeLevel::Bytes) {
+            Err(subword::VocabError::MissingToken(ref token)) => assert_eq!("x", token),
+            _ => panic!("expected a missing token error"),
+        }
+        for json in &[r#"{"\x": 0}"#, r#"{"a\q": 0}"#, "{\"a\nb\": 0}"] {
+            match Bpe::from_vocab(json, "", BpeLevel::Chars) {
+                Err(subword::VocabError::Parse { .. }) => {}
+                _ => panic!("expected a parse error for {}", json),
+            }
+        }
+        let model = Bpe::from_vocab(r#"{"\"\\\/\u00e9": 7}"#, "", BpeLevel::Chars).unwrap();
+        assert_eq!(Some(7), model.id("\"\\/é"));
+
+        let corpus = [
+            "the quick brown fox jumps over the lazy dog",
+            "  the naïve café serves crème brûlée\tto the fox\n",
+            "日本語のテキスト, and 👍🏽 emoji 🇬🇧 for the fox!",
+        ];
+        for &level in &[BpeLevel::Bytes, BpeLevel::Chars] {
+            let mut trainer = BpeTrainer::new(level, 300);
+            trainer.unk_token = Some("<unk>".to_string());
+            let model = trainer.train(corpus.iter(), filters::DefaultFilter {});
+            assert!(model.merges().len() > 5);
+            assert!(model.id("the").is_some() || model.id("\u{120}the").is_some());
+
+            for input in &corpus {
+                let result = model.encode(FilteredTokenizer::new(filters::DefaultFilter {}, input));
+                let ids: Vec<u32> = result.iter().map(|t| t.id).collect();
+                assert_eq!(*input, model.decode(&ids));
+
+                let mut end = 0;
+                for (i, piece) in result.iter().enumerate() {
+                    let token = &piece.token;
+                    assert_eq!(&input[token.start_byte..token.end_byte], token.term());
+                    assert_eq!(input[..token.start_byte].chars().count(), token.start_offset);
+                    assert!(token.start_byte >= end || token.end_byte == end);
+                    assert_eq!(i, token.position);
+                    end = token.end_byte;
+                }
+                assert_eq!(input.len(), end);
+            }
+        }
+
+        let mut trainer = BpeTrainer::new(BpeLevel::Chars, 50);
+        trainer.unk_token = Some("<unk>".to_string());
+        let model = trainer.train(corpus.iter(), filters::DefaultFilter {});
+        let result = model.encode(FilteredTokenizer::new(filters::DefaultFilter {}, "Xenon"));
+        assert_eq!(model.id("<unk>"), result.iter().find(|t| t.token.term() == "X").map(|t| t.id));
+    }
+
+    #[test]
+    fn unigram_case() {
+        use unigram::Unigram;
+
+        let vocab = "<unk>\t0\n\u{2581}\t-2\n\u{2581}the\t-1\nthe\t-3\n\u{2581}hello\t-2\n\
+                     \u{2581}hell\t-2.5\no\t-3\nh\t-4\ne\t-4\nl\t-4\n";
+        let model = Unigram::from_vocab(vocab).unwrap();
+
+        let input = "hello  thexyz";
+        let expected = [
+            (Token::from_str("hello", 0, 0), 4),
+            (Token::from_str("  the", 5, 1), 2),
+            (Token::from_str("xyz", 10, 2), 0),
+        ];
+        let result = model.encode(FilteredTokenizer::new(filters::DefaultFilter {}, input));
+
+        assert_eq!(expected.len(), result.len());
+        for (i, &(ref exp, id)) in expected.iter().enumerate() {
+            let act = result.get(i).unwrap();
+            assert_eq!(exp.term(), act.token.term());
+            assert_eq!(exp.start_offset, act.token.start_offset);
+            assert_eq!(exp.end_offset, act.token.end_offset);
+            assert_eq!(exp.position, act.token.position);
+            assert_eq!(&input[act.token.start_byte..act.token.end_byte], act.token.term());
+            assert_eq!(id, act.id);
+        }
+        let ids: Vec<u32> = result.iter().map(|t| t.id).collect();
+        assert_eq!("hello the\u{2047}", model.decode(&ids));
+
+        let nbest = model.encode_nbest(FilteredTokenizer::new(filters::DefaultFilter {}, input), 2);
+        let pieces: Vec<Vec<&str>> = nbest
+            .iter()
+            .map(|p| p.0.iter().map(|t| model.piece(t.id).unwrap().0).collect())
+            .collect();
+        assert_eq!(vec!["\u{2581}hello", "\u{2581}the", "<unk>"], pieces[0]);
+        assert_eq!(vec!["\u{2581}hell", "o", "\u{2581}the", "<unk>"], pieces[1]);
+        assert_eq!(-45.0, nbest[0].1);
+        assert_eq!(-48.5, nbest[1].1);
+
+        let mut state = 0x2545F4914F6CDD1Du64;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let mut firsts = Vec::new();
+        for _ in 0..500 {
+            let tokenizer = FilteredTokenizer::new(filters::DefaultFilter {}, input);
+            let result = model.sample(tokenizer, 1.0, &mut random);
+            let terms: String = result.iter().map(|t| t.token.term()).collect();
+            assert_eq!(input, terms);
+            firsts.push(result[0].id);
+        }
+        assert!(firsts.contains(&model.id("\u{2581}hello").unwrap()));
+        assert!(firsts.contains(&model.id("\u{2581}hell").unwrap()));
+
+        match Unigram::from_vocab("<unk>\t0\nabc -1\n") {
+            Err(subword::VocabError::Parse { line, .. }) => assert_eq!(2, line),
+            _ => panic!("expected a parse error"),
+        }
+        match Unigram::from_vocab("a\t-1\n") {
+            Err(subword::VocabError::MissingToken(ref token)) => assert_eq!("<unk>", token),
+            _ => panic!("expected a missing token error"),
+        }
+    }
 }
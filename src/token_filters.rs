@@ -0,0 +1,325 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use filters::{scan_ipv4, scan_ipv6};
+use {LineColumn, Token, TokenKind};
+
+/// A type for transforming tokens after they have been extracted by a
+/// tokenizer.
+pub trait TokenFilter {
+    /// Consumes a token and appends any resulting tokens to `out`.
+    ///
+    /// A filter that expands a token into more positions may assign positions
+    /// beyond that of the consumed token, in which case the positions of all
+    /// subsequent tokens are shifted along to make room.
+    fn filter<'a>(&self, token: Token<'a>, out: &mut VecDeque<Token<'a>>);
+}
+
+impl<F: TokenFilter + ?Sized> TokenFilter for &F {
+    fn filter<'a>(&self, token: Token<'a>, out: &mut VecDeque<Token<'a>>) {
+        (**self).filter(token, out)
+    }
+}
+
+/// An iterator that applies a TokenFilter to each token of another iterator.
+pub struct FilteredTokens<'a, I, F> {
+    tokens: I,
+    filter: F,
+    buffer: VecDeque<Token<'a>>,
+    shift: usize,
+}
+
+impl<'a, I: Iterator<Item = Token<'a>>, F: TokenFilter> FilteredTokens<'a, I, F> {
+    pub fn new(tokens: I, filter: F) -> Self {
+        FilteredTokens {
+            tokens,
+            filter,
+            buffer: VecDeque::new(),
+            shift: 0,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Token<'a>>, F: TokenFilter> Iterator for FilteredTokens<'a, I, F> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        while self.buffer.is_empty() {
+            let token = self.tokens.next()?;
+            let position = token.position;
+            self.filter.filter(token, &mut self.buffer);
+
+            let shift = self.shift;
+            let mut last = position;
+            for t in self.buffer.iter_mut() {
+                last = last.max(t.position);
+                t.position += shift;
+            }
+            self.shift += last - position;
+        }
+        self.buffer.pop_front()
+    }
+}
+
+/// Adds token filter chaining to any iterator of tokens.
+pub trait TokenFilterExt<'a>: Iterator<Item = Token<'a>> + Sized {
+    /// Applies a TokenFilter to each token of this iterator.
+    fn filter_tokens<F: TokenFilter>(self, filter: F) -> FilteredTokens<'a, Self, F> {
+        FilteredTokens::new(self, filter)
+    }
+}
+
+impl<'a, I: Iterator<Item = Token<'a>>> TokenFilterExt<'a> for I {}
+
+/// Creates a token from the bytes `start..end` of the term of another token,
+/// with offsets, bytes and location adjusted to match. If the term differs in
+/// length from the input the token covers, such as a quoted string with its
+/// escapes resolved, positions within it cannot be mapped to the input and
+/// the offsets of the whole token are kept.
+pub(crate) fn sub_token<'a>(
+    token: &Token<'a>,
+    start: usize,
+    end: usize,
+    kind: TokenKind,
+) -> Token<'a> {
+    let term = match token.term {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[start..end]),
+        Cow::Owned(ref s) => Cow::Owned(s[start..end].to_string()),
+    };
+    if token.end_byte - token.start_byte != token.term.len() {
+        return Token {
+            term,
+            kind,
+            ..*token
+        };
+    }
+    let unit = token.offset_unit;
+    let start_offset = token.start_offset + unit.measure(&token.term[..start]);
+    Token {
+        start_offset,
+        end_offset: start_offset + unit.measure(&term),
+        offset_unit: unit,
+        start_byte: token.start_byte + start,
+        end_byte: token.start_byte + end,
+        term,
+        position: token.position,
+        kind,
+        location: token.location.map(|l| LineColumn {
+            line: l.line,
+            column: l.column + (start_offset - token.start_offset),
+        }),
+    }
+}
+
+fn host_kind(host: &str) -> TokenKind {
+    let is_ip = |len: Option<usize>| len == Some(host.len());
+    if is_ip(scan_ipv4(host, 0)) || is_ip(scan_ipv6(host, 0)) {
+        TokenKind::IpAddress
+    } else {
+        TokenKind::Hostname
+    }
+}
+
+/// A token filter that follows each `TokenKind::Url` and `TokenKind::Email`
+/// token with its components at the same position.
+///
+/// URLs are broken into their scheme, host, port and each alphanumeric run of
+/// the path, query and fragment. Email addresses are broken into their local
+/// part and domain.
+pub struct UrlComponentFilter;
+
+impl TokenFilter for UrlComponentFilter {
+    fn filter<'a>(&self, token: Token<'a>, out: &mut VecDeque<Token<'a>>) {
+        let mut parts = Vec::new();
+        {
+            let term = token.term();
+            match token.kind {
+                TokenKind::Url => {
+                    let scheme_end = term.find("://").unwrap_or(0);
+                    parts.push((0, scheme_end, TokenKind::Word));
+
+                    let auth_start = scheme_end + 3;
+                    let auth_end = term[auth_start..]
+                        .find(['/', '?', '#'])
+                        .map_or(term.len(), |i| auth_start + i);
+                    let host_start = term[auth_start..auth_end]
+                        .rfind('@')
+                        .map_or(auth_start, |i| auth_start + i + 1);
+                    let bracketed = term[host_start..].starts_with('[');
+                    let (host_start, host_end, port_start) = if bracketed {
+                        let close = term[host_start..auth_end]
+                            .find(']')
+                            .map_or(auth_end, |i| host_start + i);
+                        (host_start + 1, close, (close + 2).min(auth_end))
+                    } else {
+                        let colon = term[host_start..auth_end]
+                            .find(':')
+                            .map_or(auth_end, |i| host_start + i);
+                        (host_start, colon, (colon + 1).min(auth_end))
+                    };
+                    parts.push((host_start, host_end, host_kind(&term[host_start..host_end])));
+                    parts.push((port_start, auth_end, TokenKind::Number));
+
+                    let mut run_start = None;
+                    for (i, c) in term[auth_end..].char_indices() {
+                        match (c.is_alphanumeric(), run_start) {
+                            (true, None) => run_start = Some(auth_end + i),
+                            (false, Some(s)) => {
+                                parts.push((s, auth_end + i, TokenKind::Word));
+                                run_start = None;
+                            }
+                            _ => {}
+                        }
+                    }
+                    if let Some(s) = run_start {
+                        parts.push((s, term.len(), TokenKind::Word));
+                    }
+                }
+                TokenKind::Email => {
+                    let at = term.rfind('@').unwrap_or(0);
+                    parts.push((0, at, TokenKind::Word));
+                    parts.push((at + 1, term.len(), TokenKind::Hostname));
+                }
+                _ => {}
+            }
+        }
+
+        let subs: Vec<Token<'a>> = parts
+            .into_iter()
+            .filter(|&(start, end, _)| start < end)
+            .map(|(start, end, kind)| sub_token(&token, start, end, kind))
+            .collect();
+        out.push_back(token);
+        out.extend(subs);
+    }
+}
+
+/// A token filter that splits identifiers from source code into their parts,
+/// such that `parseHTTPResponse2` becomes `parse`, `HTTP`, `Response` and `2`,
+/// and `some_text` becomes `some` and `text`.
+///
+/// Parts are given consecutive positions starting at the position of the
+/// original token, and a token without any parts, such as `__`, is passed
+/// through whole. Since the default filter extracts underscores as tokens of
+/// their own this is best paired with a tokenizer filter that keeps
+/// identifiers whole, such as `filters::WhitespaceFilter`.
+pub struct IdentifierSplitFilter {
+    /// Split where lower case is followed by upper case, as in `camelCase`, and
+    /// before the last capital of an upper case run, as in `HTTPResponse`.
+    pub split_on_case_change: bool,
+
+    /// Split where letters and digits meet, as in `utf8` or `2nd`.
+    pub split_on_numerics: bool,
+
+    /// Split on and discard underscores, as in `snake_case`.
+    pub split_on_underscores: bool,
+
+    /// Emit the original token, at the position of the first part, before its
+    /// parts whenever it is split.
+    pub preserve_original: bool,
+}
+
+impl Default for IdentifierSplitFilter {
+    fn default() -> Self {
+        IdentifierSplitFilter {
+            split_on_case_change: true,
+            split_on_numerics: true,
+            split_on_underscores: true,
+            preserve_original: false,
+        }
+    }
+}
+
+impl IdentifierSplitFilter {
+    fn is_boundary(&self, prev: char, c: char, next: Option<char>) -> bool {
+        if self.split_on_case_change {
+            if prev.is_lowercase() && c.is_uppercase() {
+                return true;
+            }
+            if prev.is_uppercase() && c.is_uppercase() && next.is_some_and(char::is_lowercase) {
+                return true;
+            }
+        }
+        self.split_on_numerics &&
+            ((prev.is_alphabetic() && c.is_numeric()) || (prev.is_numeric() && c.is_alphabetic()))
+    }
+
+    /// Returns the byte ranges of each part of an identifier.
+    fn split(&self, term: &str) -> Vec<(usize, usize)> {
+        let mut parts = Vec::new();
+        let mut start = None;
+        let mut prev: Option<char> = None;
+        let mut chars = term.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            let next = chars.peek().map(|&(_, n)| n);
+            if c == '_' && self.split_on_underscores {
+                if let Some(s) = start.take() {
+                    parts.push((s, i));
+                }
+            } else {
+                match (start, prev) {
+                    (None, _) => start = Some(i),
+                    (Some(s), Some(p)) if self.is_boundary(p, c, next) => {
+                        parts.push((s, i));
+                        start = Some(i);
+                    }
+                    _ => {}
+                }
+            }
+            prev = Some(c);
+        }
+        if let Some(s) = start {
+            parts.push((s, term.len()));
+        }
+        parts
+    }
+}
+
+impl TokenFilter for IdentifierSplitFilter {
+    fn filter<'a>(&self, token: Token<'a>, out: &mut VecDeque<Token<'a>>) {
+        let parts = self.split(token.term());
+        if parts.is_empty() || parts == [(0, token.term.len())] {
+            out.push_back(token);
+            return;
+        }
+
+        let subs: Vec<Token<'a>> = parts
+            .into_iter()
+            .enumerate()
+            .map(|(i, (start, end))| {
+                let is_number = token.term[start..end].bytes().all(|b| b.is_ascii_digit());
+                let kind = if is_number { TokenKind::Number } else { token.kind };
+                let mut sub = sub_token(&token, start, end, kind);
+                sub.position += i;
+                sub
+            })
+            .collect();
+        if self.preserve_original {
+            out.push_back(token);
+        }
+        out.extend(subs);
+    }
+}
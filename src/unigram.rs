@@ -0,0 +1,440 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+
+use filters::Filter;
+use subword::{read_vocab, SubToken, VocabError};
+use token_filters::sub_token;
+use {FilteredTokenizer, Token, TokenKind};
+
+/// The marker that represents whitespace in the pieces of a model.
+const MARKER: &str = "\u{2581}";
+
+/// A token of the input along with the discarded input that precedes it,
+/// which is represented in the lattice by a leading marker.
+struct Word<'a> {
+    token: Token<'a>,
+    gap_start: usize,
+    gap_offset: usize,
+    gap: usize,
+    marker: usize,
+    text_start: usize,
+}
+
+/// The text of an input as the model sees it, where each word is preceded by
+/// a marker, along with the char boundaries that pieces may start and end at.
+struct Lattice<'a> {
+    input: &'a str,
+    words: Vec<Word<'a>>,
+    text: String,
+    bounds: Vec<usize>,
+    word_of: Vec<usize>,
+    word_end: Vec<usize>,
+}
+
+/// A piece of a path through a lattice, from one char boundary to another.
+type Edge = (usize, usize, u32);
+
+fn by_score(a: &f64, b: &f64) -> Ordering {
+    b.partial_cmp(a).unwrap_or(Ordering::Equal)
+}
+
+fn log_sum_exp(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        return b;
+    }
+    let (hi, lo) = if a > b { (a, b) } else { (b, a) };
+    hi + (lo - hi).exp().ln_1p()
+}
+
+/// A SentencePiece compatible unigram language model, which splits each
+/// token into the sequence of pieces from its vocabulary with the highest
+/// total score, where the score of a piece is its log probability.
+///
+/// As with SentencePiece, the discarded input before each token is
+/// represented by the marker `▁` (U+2581), such that `Hello world` is seen as
+/// `▁Hello▁world` and may be split into `▁Hello`, `▁wor` and `ld`. Pieces
+/// never span more than one token, and the term of each piece is the text it
+/// covers in the input, so the term of `▁wor` is ` wor`. Discarded input
+/// after the last token is not encoded.
+///
+/// Chars that are not covered by any piece are encoded as the unknown token,
+/// with consecutive unknown chars of a token encoded together.
+pub struct Unigram {
+    vocab: HashMap<String, u32>,
+    pieces: Vec<(String, f64)>,
+    unk_id: u32,
+    unk_score: f64,
+    max_piece_chars: usize,
+
+    /// Whether a marker is added before the first token when no input
+    /// precedes it, as SentencePiece does by default.
+    pub add_dummy_prefix: bool,
+}
+
+impl Unigram {
+    /// Creates a model from a list of pieces and their scores, where the id
+    /// of each piece is its index in the list, and which must contain
+    /// `unk_token`.
+    pub fn new(pieces: Vec<(String, f64)>, unk_token: &str) -> Result<Self, VocabError> {
+        let mut vocab = HashMap::new();
+        for (id, piece) in pieces.iter().enumerate() {
+            vocab.entry(piece.0.clone()).or_insert(id as u32);
+        }
+        let unk_id = match vocab.get(unk_token) {
+            Some(id) => *id,
+            None => return Err(VocabError::MissingToken(unk_token.to_string())),
+        };
+        let min_score = pieces.iter().map(|p| p.1).fold(0.0, f64::min);
+        Ok(Unigram {
+            max_piece_chars: pieces.iter().map(|p| p.0.chars().count()).max().unwrap_or(1),
+            vocab,
+            pieces,
+            unk_id,
+            unk_score: min_score - 10.0,
+            add_dummy_prefix: true,
+        })
+    }
+
+    /// Creates a model from the contents of a SentencePiece `.vocab` file,
+    /// which lists one piece per line followed by a tab and its score, with
+    /// ids counting from 0 and `<unk>` as the unknown token.
+    pub fn from_vocab(vocab: &str) -> Result<Self, VocabError> {
+        let mut pieces = Vec::new();
+        for (i, line) in vocab.lines().enumerate() {
+            let parsed = line
+                .rsplit_once('\t')
+                .filter(|p| !p.0.is_empty())
+                .and_then(|(piece, score)| score.trim().parse().ok().map(|s| (piece, s)));
+            match parsed {
+                Some((piece, score)) => pieces.push((piece.to_string(), score)),
+                None => {
+                    return Err(VocabError::Parse {
+                        line: i + 1,
+                        message: "expected a piece and a score separated by a tab".to_string(),
+                    })
+                }
+            }
+        }
+        Unigram::new(pieces, "<unk>")
+    }
+
+    /// Creates a model from a SentencePiece `.vocab` file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, VocabError> {
+        Unigram::from_vocab(&read_vocab(path)?)
+    }
+
+    /// Returns the id of a piece.
+    pub fn id(&self, piece: &str) -> Option<u32> {
+        self.vocab.get(piece).cloned()
+    }
+
+    /// Returns the piece and score of an id.
+    pub fn piece(&self, id: u32) -> Option<(&str, f64)> {
+        self.pieces.get(id as usize).map(|p| (p.0.as_str(), p.1))
+    }
+
+    /// Collects the tokens of a tokenizer into a lattice.
+    fn lattice<'a, T: Filter>(&self, mut tokenizer: FilteredTokenizer<'a, T>) -> Lattice<'a> {
+        let input = tokenizer.input;
+        let state = tokenizer.state();
+        let mut lattice = Lattice {
+            input,
+            words: Vec::new(),
+            text: String::new(),
+            bounds: Vec::new(),
+            word_of: Vec::new(),
+            word_end: Vec::new(),
+        };
+        let (mut gap_start, mut gap_offset) = (state.byte_offset, state.char_offset);
+
+        for token in tokenizer.by_ref().filter(|t| t.kind != TokenKind::Trivia) {
+            let gap = token.start_byte - gap_start;
+            let text_start = lattice.text.len();
+            let marker = if gap > 0 || (lattice.words.is_empty() && self.add_dummy_prefix) {
+                lattice.text.push_str(MARKER);
+                MARKER.len()
+            } else {
+                0
+            };
+            lattice.text.push_str(&token.term);
+
+            let index = lattice.words.len();
+            let first = lattice.bounds.len();
+            let bounds = lattice.text[text_start..].char_indices().map(|c| c.0 + text_start);
+            lattice.bounds.extend(bounds);
+            let last = lattice.bounds.len();
+            lattice.word_of.extend((first..last).map(|_| index));
+            lattice.word_end.extend((first..last).map(|_| last));
+
+            let next = (token.end_byte, token.end_offset);
+            lattice.words.push(Word {
+                token,
+                gap_start,
+                gap_offset,
+                gap,
+                marker,
+                text_start,
+            });
+            gap_start = next.0;
+            gap_offset = next.1;
+        }
+        lattice.bounds.push(lattice.text.len());
+        lattice
+    }
+
+    /// Calls `f` with the end, id and score of each piece that starts at the
+    /// char boundary `start` of a lattice.
+    fn edges<F: FnMut(usize, u32, f64)>(&self, lattice: &Lattice, start: usize, mut f: F) {
+        let end = lattice.word_end[start].min(start + self.max_piece_chars);
+        let mut single = false;
+        for next in start + 1..=end {
+            let piece = &lattice.text[lattice.bounds[start]..lattice.bounds[next]];
+            match self.vocab.get(piece) {
+                Some(&id) if id != self.unk_id => {
+                    single |= next == start + 1;
+                    f(next, id, self.pieces[id as usize].1);
+                }
+                _ => {}
+            }
+        }
+        if !single {
+            f(start + 1, self.unk_id, self.unk_score);
+        }
+    }
+
+    /// Returns up to `n` paths through a lattice with the highest scores, in
+    /// order, along with their scores.
+    fn nbest_paths(&self, lattice: &Lattice, n: usize) -> Vec<(Vec<Edge>, f64)> {
+        let len = lattice.bounds.len() - 1;
+        let n = n.max(1);
+
+        // For each char boundary, the best paths that end there, as their
+        // score, the boundary and rank of the path they extend, and the id of
+        // their last piece.
+        let mut best: Vec<Vec<(f64, usize, usize, u32)>> = vec![Vec::new(); len + 1];
+        best[0].push((0.0, 0, 0, self.unk_id));
+        for start in 0..len {
+            best[start].sort_by(|a, b| by_score(&a.0, &b.0));
+            best[start].truncate(n);
+            let (before, after) = best.split_at_mut(start + 1);
+            let paths = &before[start];
+            self.edges(lattice, start, |end, id, score| {
+                for (rank, path) in paths.iter().enumerate() {
+                    after[end - start - 1].push((path.0 + score, start, rank, id));
+                }
+            });
+        }
+        best[len].sort_by(|a, b| by_score(&a.0, &b.0));
+        best[len].truncate(n);
+
+        best[len]
+            .iter()
+            .enumerate()
+            .map(|(rank, path)| {
+                let mut edges = Vec::new();
+                let (mut end, mut rank) = (len, rank);
+                while end > 0 {
+                    let (_, start, prev_rank, id) = best[end][rank];
+                    edges.push((start, end, id));
+                    end = start;
+                    rank = prev_rank;
+                }
+                edges.reverse();
+                (edges, path.0)
+            })
+            .collect()
+    }
+
+    /// Samples a path through a lattice with probability proportional to its
+    /// score multiplied by `alpha`, by computing the total probability of the
+    /// paths that end at each char boundary and then sampling pieces from the
+    /// end of the lattice backwards.
+    fn sample_path<R>(&self, lattice: &Lattice, alpha: f64, mut random: R) -> Vec<Edge>
+    where
+        R: FnMut() -> f64,
+    {
+        let len = lattice.bounds.len() - 1;
+        let mut incoming: Vec<Vec<(usize, u32, f64)>> = vec![Vec::new(); len + 1];
+        let mut forward = vec![f64::NEG_INFINITY; len + 1];
+        forward[0] = 0.0;
+        for start in 0..len {
+            if start > 0 {
+                forward[start] = incoming[start]
+                    .iter()
+                    .fold(f64::NEG_INFINITY, |acc, e| log_sum_exp(acc, forward[e.0] + e.2));
+            }
+            self.edges(lattice, start, |end, id, score| {
+                incoming[end].push((start, id, alpha * score))
+            });
+        }
+        if len > 0 {
+            forward[len] = incoming[len]
+                .iter()
+                .fold(f64::NEG_INFINITY, |acc, e| log_sum_exp(acc, forward[e.0] + e.2));
+        }
+
+        let mut path = Vec::new();
+        let mut end = len;
+        while end > 0 {
+            let mut r = random();
+            let edges = &incoming[end];
+            let mut chosen = edges[edges.len() - 1];
+            for e in edges {
+                r -= (forward[e.0] + e.2 - forward[end]).exp();
+                if r < 0.0 {
+                    chosen = *e;
+                    break;
+                }
+            }
+            path.push((chosen.0, end, chosen.1));
+            end = chosen.0;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Creates the pieces of a path through a lattice, with consecutive
+    /// unknown pieces of a word joined together.
+    fn sub_tokens<'a>(&self, lattice: &Lattice<'a>, path: &[Edge]) -> Vec<SubToken<'a>> {
+        let mut joined: Vec<Edge> = Vec::with_capacity(path.len());
+        for &(start, end, id) in path {
+            match joined.last_mut() {
+                Some(last) if id == self.unk_id &&
+                    last.2 == self.unk_id &&
+                    lattice.word_of[last.0] == lattice.word_of[start] =>
+                {
+                    last.1 = end
+                }
+                _ => joined.push((start, end, id)),
+            }
+        }
+
+        let mut out = Vec::with_capacity(joined.len());
+        for (start, end, id) in joined {
+            let word = &lattice.words[lattice.word_of[start]];
+            let to_input = |i: usize| {
+                let i = lattice.bounds[i] - word.text_start;
+                if i < word.marker {
+                    0
+                } else {
+                    word.gap + i - word.marker
+                }
+            };
+            let (start, end) = (to_input(start), to_input(end));
+            let token = &word.token;
+            let mut piece = if start >= word.gap {
+                sub_token(token, start - word.gap, end - word.gap, token.kind)
+            } else {
+                let gap = &lattice.input[word.gap_start..token.start_byte];
+                let term = match token.term {
+                    Cow::Borrowed(_) => {
+                        Cow::Borrowed(&lattice.input[word.gap_start..token.end_byte])
+                    }
+                    Cow::Owned(ref term) => Cow::Owned(format!("{}{}", gap, term)),
+                };
+                let whole = Token {
+                    term,
+                    start_offset: word.gap_offset,
+                    end_offset: token.end_offset,
+                    offset_unit: token.offset_unit,
+                    start_byte: word.gap_start,
+                    end_byte: token.end_byte,
+                    position: 0,
+                    kind: token.kind,
+                    location: None,
+                };
+                sub_token(&whole, start, end, token.kind)
+            };
+            piece.position = out.len();
+            out.push(SubToken { token: piece, id });
+        }
+        out
+    }
+
+    /// Encodes the tokens of a tokenizer as the pieces with the highest total
+    /// score.
+    pub fn encode<'a, T: Filter>(&self, tokenizer: FilteredTokenizer<'a, T>) -> Vec<SubToken<'a>> {
+        let lattice = self.lattice(tokenizer);
+        match self.nbest_paths(&lattice, 1).first() {
+            Some(path) => self.sub_tokens(&lattice, &path.0),
+            None => Vec::new(),
+        }
+    }
+
+    /// Encodes the tokens of a tokenizer as up to `n` distinct sequences of
+    /// pieces with the highest total scores, in order, along with their
+    /// scores.
+    pub fn encode_nbest<'a, T: Filter>(
+        &self,
+        tokenizer: FilteredTokenizer<'a, T>,
+        n: usize,
+    ) -> Vec<(Vec<SubToken<'a>>, f64)> {
+        let lattice = self.lattice(tokenizer);
+        self.nbest_paths(&lattice, n)
+            .into_iter()
+            .map(|(path, score)| (self.sub_tokens(&lattice, &path), score))
+            .collect()
+    }
+
+    /// Encodes the tokens of a tokenizer as a sequence of pieces sampled at
+    /// random, for subword regularization. Each sequence is sampled with
+    /// probability proportional to its total score multiplied by `alpha`,
+    /// such that smaller values of `alpha` give more varied sequences.
+    ///
+    /// `random` must return numbers distributed uniformly from 0 up to but
+    /// excluding 1, such as `|| rng.gen()` with the `rand` crate.
+    pub fn sample<'a, T, R>(
+        &self,
+        tokenizer: FilteredTokenizer<'a, T>,
+        alpha: f64,
+        random: R,
+    ) -> Vec<SubToken<'a>>
+    where
+        T: Filter,
+        R: FnMut() -> f64,
+    {
+        let lattice = self.lattice(tokenizer);
+        let path = self.sample_path(&lattice, alpha, random);
+        self.sub_tokens(&lattice, &path)
+    }
+
+    /// Decodes a sequence of ids back into text, with markers replaced by
+    /// spaces. Ids missing from the vocabulary are skipped, and the unknown
+    /// token is decoded as `⁇` (U+2047).
+    pub fn decode(&self, ids: &[u32]) -> String {
+        let mut text = String::new();
+        for &id in ids {
+            match self.pieces.get(id as usize) {
+                Some(_) if id == self.unk_id => text.push('\u{2047}'),
+                Some(piece) => text.push_str(&piece.0.replace(MARKER, " ")),
+                None => {}
+            }
+        }
+        if self.add_dummy_prefix && text.starts_with(' ') {
+            text.remove(0);
+        }
+        text
+    }
+}
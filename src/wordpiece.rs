@@ -0,0 +1,145 @@
+// Copyright (c) 2017 Ashley Jeffs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use subword::{read_vocab, SubToken, VocabError};
+use token_filters::sub_token;
+use Token;
+
+/// A BERT style WordPiece model, which splits each token into the longest
+/// pieces found in its vocabulary from left to right, such that `unaffable`
+/// becomes `un`, `##aff` and `##able`.
+///
+/// Pieces that continue a word are looked up with a prefix, `##` by default,
+/// while their terms are the text they cover without it. A token that cannot
+/// be split into pieces of the vocabulary, or that is longer than
+/// `max_chars_per_word`, is encoded whole as the unknown token.
+pub struct WordPiece {
+    vocab: HashMap<String, u32>,
+    unk_id: u32,
+
+    /// The prefix of pieces that continue a word.
+    pub continuing_prefix: String,
+
+    /// The number of chars beyond which a token is encoded as unknown.
+    pub max_chars_per_word: usize,
+}
+
+impl WordPiece {
+    /// Creates a model from a vocabulary of pieces and their ids, which must
+    /// contain `unk_token`.
+    pub fn new(vocab: HashMap<String, u32>, unk_token: &str) -> Result<Self, VocabError> {
+        let unk_id = match vocab.get(unk_token) {
+            Some(id) => *id,
+            None => return Err(VocabError::MissingToken(unk_token.to_string())),
+        };
+        Ok(WordPiece {
+            vocab,
+            unk_id,
+            continuing_prefix: "##".to_string(),
+            max_chars_per_word: 100,
+        })
+    }
+
+    /// Creates a model from the contents of a `vocab.txt` file, which lists
+    /// one piece per line with ids counting from 0, with `[UNK]` as the
+    /// unknown token.
+    pub fn from_vocab(vocab: &str) -> Result<Self, VocabError> {
+        let vocab = vocab
+            .lines()
+            .enumerate()
+            .map(|(id, piece)| (piece.to_string(), id as u32))
+            .collect();
+        WordPiece::new(vocab, "[UNK]")
+    }
+
+    /// Creates a model from a `vocab.txt` file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, VocabError> {
+        WordPiece::from_vocab(&read_vocab(path)?)
+    }
+
+    /// Returns the id of a piece, including any continuing prefix.
+    pub fn id(&self, piece: &str) -> Option<u32> {
+        self.vocab.get(piece).cloned()
+    }
+
+    /// Splits a term into the byte ranges and ids of its pieces, or returns
+    /// `None` if it cannot be split.
+    fn split(&self, term: &str) -> Option<Vec<(usize, usize, u32)>> {
+        if term.chars().count() > self.max_chars_per_word {
+            return None;
+        }
+        let mut pieces = Vec::new();
+        let mut key = String::new();
+        let mut start = 0;
+        while start < term.len() {
+            let mut end = term.len();
+            let id = loop {
+                key.clear();
+                if start > 0 {
+                    key.push_str(&self.continuing_prefix);
+                }
+                key.push_str(&term[start..end]);
+                if let Some(id) = self.vocab.get(&key) {
+                    break *id;
+                }
+                end = term[start..end].char_indices().next_back()?.0 + start;
+                if end == start {
+                    return None;
+                }
+            };
+            pieces.push((start, end, id));
+            start = end;
+        }
+        Some(pieces)
+    }
+
+    /// Encodes a token, appending its pieces to `out`.
+    pub fn encode_token<'a>(&self, token: &Token<'a>, out: &mut Vec<SubToken<'a>>) {
+        match self.split(&token.term) {
+            Some(pieces) => {
+                for (start, end, id) in pieces {
+                    let mut piece = sub_token(token, start, end, token.kind);
+                    piece.position = out.len();
+                    out.push(SubToken { token: piece, id });
+                }
+            }
+            None => {
+                let mut piece = sub_token(token, 0, token.term.len(), token.kind);
+                piece.position = out.len();
+                out.push(SubToken {
+                    token: piece,
+                    id: self.unk_id,
+                });
+            }
+        }
+    }
+
+    /// Encodes a sequence of tokens, such as those of a FilteredTokenizer.
+    pub fn encode<'a, I: IntoIterator<Item = Token<'a>>>(&self, tokens: I) -> Vec<SubToken<'a>> {
+        let mut out = Vec::new();
+        for token in tokens {
+            self.encode_token(&token, &mut out);
+        }
+        out
+    }
+}